@@ -0,0 +1,78 @@
+// Plays whole games to a full board, so most steps are taken with a long
+// body. That is where checking cells against the body costs the most, in
+// collisions and food placement. The snake is steered round a fixed cycle
+// through every cell rather than by the autopilot, whose path search would
+// otherwise take up most of the time.
+//
+// Run it with and without the `occupancy-bitset` feature to compare the
+// body scan against the bitset:
+//
+//     cargo bench --bench game
+//     cargo bench --bench game --features occupancy-bitset
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rusty_snake::{Board, Direction, Game, GameConfig, Position};
+
+// Stops a game that somehow never ends
+const MAX_STEPS: usize = 1_000_000;
+
+// The next move on a cycle through every inside cell of a board whose inside
+// is `width` cells wide and an even number of cells high: right and left
+// along the rows from column 2, then back up column 1 to the top
+fn cycle_direction(head: Position, width: usize, height: usize) -> Direction {
+    match head {
+        Position { x: 1, y: 1 } => Direction::Right,
+        Position { x: 1, .. } => Direction::Up,
+        Position { x, y } if y % 2 == 1 => {
+            if x < width {
+                Direction::Right
+            } else {
+                Direction::Down
+            }
+        }
+        Position { x, y } => {
+            if x > 2 || y == height {
+                Direction::Left
+            } else {
+                Direction::Down
+            }
+        }
+    }
+}
+
+fn play(config: &GameConfig) -> Game {
+    let (width, height) = (config.board.width - 2, config.board.height - 2);
+    let mut game = Game::new(config.clone());
+    for _ in 0..MAX_STEPS {
+        if game.is_game_over() || game.is_won() {
+            break;
+        }
+        game.turn(cycle_direction(game.snake().body()[0], width, height));
+        game.step();
+    }
+    game
+}
+
+fn full_game(c: &mut Criterion) {
+    let boards = [
+        ("small", Board::new(14, 10)),
+        ("medium", Board::new(26, 18)),
+    ];
+
+    let mut group = c.benchmark_group("full_game");
+    group.sample_size(10);
+    for (name, board) in boards {
+        let config = GameConfig {
+            board,
+            seed: Some(0),
+            ..GameConfig::default()
+        };
+        group.bench_with_input(BenchmarkId::new("cycle", name), &config, |b, config| {
+            b.iter(|| play(config));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, full_game);
+criterion_main!(benches);
@@ -0,0 +1,75 @@
+// Compares the two ways of storing the snake body: a Vec that shifts every
+// segment with `insert(0, ..)` on each move, and the VecDeque the game uses
+// now, which pushes and pops at either end in constant time.
+//
+// Each move mirrors `Snake::update` for a snake that isn't growing: the new
+// head goes on the front and the tail comes off the back.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rusty_snake::{Position, GRID_HEIGHT, GRID_WIDTH};
+use std::collections::VecDeque;
+
+const MOVES: usize = 1_000;
+
+// A body `length` cells long laid out row by row across the default board
+fn body(length: usize) -> Vec<Position> {
+    (0..length)
+        .map(|i| Position {
+            x: i % GRID_WIDTH,
+            y: i / GRID_WIDTH,
+        })
+        .collect()
+}
+
+// Walks the head along the top row so every move lands on a fresh position
+fn step(head: Position) -> Position {
+    Position {
+        x: (head.x + 1) % GRID_WIDTH,
+        y: head.y,
+    }
+}
+
+fn move_vec(body: &mut Vec<Position>) {
+    let head = step(body[0]);
+    body.insert(0, head);
+    body.pop();
+}
+
+fn move_deque(body: &mut VecDeque<Position>) {
+    let head = step(body[0]);
+    body.push_front(head);
+    body.pop_back();
+}
+
+fn snake_update(c: &mut Criterion) {
+    let cells = GRID_WIDTH * GRID_HEIGHT;
+    let sizes = [("short", 4), ("medium", cells / 8), ("long", cells / 2)];
+
+    let mut group = c.benchmark_group("snake_update");
+    for (name, length) in sizes {
+        group.bench_with_input(BenchmarkId::new("vec", name), &length, |b, &length| {
+            let mut snake = body(length);
+            b.iter(|| {
+                for _ in 0..MOVES {
+                    move_vec(black_box(&mut snake));
+                }
+            });
+        });
+        group.bench_with_input(
+            BenchmarkId::new("vec_deque", name),
+            &length,
+            |b, &length| {
+                let mut snake = VecDeque::from(body(length));
+                b.iter(|| {
+                    for _ in 0..MOVES {
+                        move_deque(black_box(&mut snake));
+                    }
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, snake_update);
+criterion_main!(benches);
@@ -0,0 +1,289 @@
+// Computer control for the snake. Both strategies search the board with a
+// breadth-first search over open cells, treating walls and the body as
+// blocked. The tail is treated as open unless the snake is about to grow,
+// since it moves out of the way on the same tick the head moves in.
+//
+// Ties between equally good moves are broken by the order neighbors are
+// explored in, which is shuffled once per decision with the caller's RNG.
+// Seeding that RNG makes a whole autopilot run reproducible.
+
+use crate::{Board, Direction, Position, Snake};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::VecDeque;
+
+/// How the autopilot picks its next move.
+#[derive(Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+pub enum AutopilotStrategy {
+    /// Take the shortest path to the food, even into a dead end.
+    Greedy,
+    /// Only follow a path to the food if the snake can still reach its tail
+    /// after eating; otherwise stall by following the tail.
+    #[default]
+    Safe,
+}
+
+// Occupancy of every board cell, indexed by `y * width + x`
+struct Grid {
+    width: usize,
+    blocked: Vec<bool>,
+    order: [Direction; 4],
+}
+
+impl Grid {
+    fn new<'a>(
+        board: &Board,
+        body: impl ExactSizeIterator<Item = &'a Position>,
+        growing: bool,
+        order: [Direction; 4],
+    ) -> Self {
+        let mut blocked = vec![false; board.width * board.height];
+        for y in 0..board.height {
+            for x in 0..board.width {
+                blocked[y * board.width + x] = board.is_wall(Position { x, y });
+            }
+        }
+
+        let mut grid = Grid {
+            width: board.width,
+            blocked,
+            order,
+        };
+        let occupied = if growing { body.len() } else { body.len() - 1 };
+        for segment in body.take(occupied) {
+            grid.set(*segment, true);
+        }
+        grid
+    }
+
+    fn is_blocked(&self, position: Position) -> bool {
+        self.blocked[position.y * self.width + position.x]
+    }
+
+    fn set(&mut self, position: Position, blocked: bool) {
+        self.blocked[position.y * self.width + position.x] = blocked;
+    }
+
+    fn open_neighbors(
+        &self,
+        position: Position,
+    ) -> impl Iterator<Item = (Direction, Position)> + '_ {
+        self.order
+            .into_iter()
+            .map(move |direction| (direction, position.neighbor(direction)))
+            .filter(|(_, next)| !self.is_blocked(*next))
+    }
+
+    // Shortest path from `from` to `to`, excluding `from` and including `to`.
+    // The first step may not go in the `forbidden` direction.
+    fn path(
+        &self,
+        from: Position,
+        to: Position,
+        forbidden: Option<Direction>,
+    ) -> Option<Vec<Position>> {
+        let mut previous: Vec<Option<Position>> = vec![None; self.blocked.len()];
+        let mut queue = VecDeque::from([from]);
+        let mut seen = vec![false; self.blocked.len()];
+        seen[from.y * self.width + from.x] = true;
+
+        while let Some(position) = queue.pop_front() {
+            if position == to {
+                let mut path = vec![to];
+                let mut current = to;
+                while let Some(step) = previous[current.y * self.width + current.x] {
+                    if step == from {
+                        break;
+                    }
+                    path.push(step);
+                    current = step;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for (direction, next) in self.open_neighbors(position) {
+                if position == from && Some(direction) == forbidden {
+                    continue;
+                }
+                let index = next.y * self.width + next.x;
+                if !seen[index] {
+                    seen[index] = true;
+                    previous[index] = Some(position);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    // Number of open cells reachable from `from`
+    fn reachable_area(&self, from: Position) -> usize {
+        let mut queue = VecDeque::from([from]);
+        let mut seen = vec![false; self.blocked.len()];
+        seen[from.y * self.width + from.x] = true;
+        let mut area = 0;
+
+        while let Some(position) = queue.pop_front() {
+            area += 1;
+            for (_, next) in self.open_neighbors(position) {
+                let index = next.y * self.width + next.x;
+                if !seen[index] {
+                    seen[index] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+        area
+    }
+}
+
+/// Picks the direction for the next tick, or `None` if every move is fatal.
+/// With no `food` to aim for, the snake follows its tail. Cells in `avoid`
+/// are steered around like walls.
+pub fn next_direction(
+    strategy: AutopilotStrategy,
+    board: &Board,
+    snake: &Snake,
+    food: Option<Position>,
+    avoid: &[Position],
+    rng: &mut impl Rng,
+) -> Option<Direction> {
+    let mut order = Direction::ALL;
+    order.shuffle(rng);
+
+    let head = snake.body[0];
+    let mut grid = Grid::new(board, snake.body.iter(), snake.pending_growth > 0, order);
+    for cell in avoid {
+        grid.set(*cell, true);
+    }
+    let reverse = Some(snake.direction.opposite());
+
+    if let Some(path) = food.and_then(|food| grid.path(head, food, reverse)) {
+        if strategy == AutopilotStrategy::Greedy
+            || can_reach_tail_after(board, snake, &path, avoid, order)
+        {
+            return head.direction_to(path[0]);
+        }
+    }
+
+    stall(board, snake, &grid)
+}
+
+// Replays `path` on a copy of the body and checks that, once the food at the
+// end of it is eaten, the head can still find its way to the tail.
+fn can_reach_tail_after(
+    board: &Board,
+    snake: &Snake,
+    path: &[Position],
+    avoid: &[Position],
+    order: [Direction; 4],
+) -> bool {
+    let mut body = snake.body.clone();
+    let mut pending_growth = snake.pending_growth;
+    for step in path {
+        body.push_front(*step);
+        if pending_growth > 0 {
+            pending_growth -= 1;
+        } else {
+            body.pop_back();
+        }
+    }
+
+    let tail = body[body.len() - 1];
+    let mut grid = Grid::new(board, body.iter(), true, order);
+    for cell in avoid {
+        grid.set(*cell, true);
+    }
+    grid.set(tail, false);
+    grid.path(body[0], tail, None).is_some()
+}
+
+// With no safe path to the food, chase the tail: move to the open neighbor
+// that keeps the tail in reach by the longest route, leaving the most room on
+// ties. Following the tail reshapes the body until a safe path appears.
+fn stall(board: &Board, snake: &Snake, grid: &Grid) -> Option<Direction> {
+    let head = snake.body[0];
+    let mut best: Option<((bool, usize, usize), Direction)> = None;
+
+    for (direction, next) in grid.open_neighbors(head) {
+        if direction == snake.direction.opposite() {
+            continue;
+        }
+
+        let mut body = snake.body.clone();
+        body.push_front(next);
+        if snake.pending_growth == 0 {
+            body.pop_back();
+        }
+        let tail = body[body.len() - 1];
+        let mut after = Grid::new(board, body.iter(), true, grid.order);
+        let area = after.reachable_area(next);
+        after.set(tail, false);
+        let to_tail = after.path(next, tail, None).map(|path| path.len());
+
+        let score = (to_tail.is_some(), to_tail.unwrap_or(0), area);
+        if best.is_none_or(|(best_score, _)| score > best_score) {
+            best = Some((score, direction));
+        }
+    }
+
+    best.map(|(_, direction)| direction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Game, GameConfig, GameEvent, TimedEvent};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    // Every heading and event of an autopilot game over `ticks` steps
+    fn autopilot_run(seed: u64, ticks: usize) -> (Vec<Direction>, Vec<TimedEvent>) {
+        let mut game = Game::new(GameConfig {
+            seed: Some(seed),
+            autopilot: Some(AutopilotStrategy::Safe),
+            ..GameConfig::default()
+        });
+        let mut directions = Vec::new();
+        let mut events = Vec::new();
+        for _ in 0..ticks {
+            events.extend(game.step());
+            directions.push(game.snake().direction());
+        }
+        (directions, events)
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_run() {
+        let (directions, events) = autopilot_run(42, 300);
+        assert!(events
+            .iter()
+            .any(|event| matches!(event.event, GameEvent::Ate(_))));
+        assert_eq!((directions, events), autopilot_run(42, 300));
+    }
+
+    #[test]
+    fn seed_decides_ties() {
+        // Food one cell diagonally away can be reached going either way first
+        let board = Board::default();
+        let head = board.center();
+        let snake = Snake::init(vec![head]).unwrap();
+        let food = head.neighbor(Direction::Down).neighbor(Direction::Right);
+        let first_moves: Vec<Option<Direction>> = (0..32)
+            .map(|seed| {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                next_direction(
+                    AutopilotStrategy::Safe,
+                    &board,
+                    &snake,
+                    Some(food),
+                    &[],
+                    &mut rng,
+                )
+            })
+            .collect();
+        assert!(first_moves.contains(&Some(Direction::Down)));
+        assert!(first_moves.contains(&Some(Direction::Right)));
+    }
+}
@@ -0,0 +1,220 @@
+// Backdrops drawn beneath the board in place of the theme's plain background
+// color. Each one is painted into a window-sized buffer once, when the game
+// starts or the theme changes, and that buffer is copied into every frame.
+
+use crate::{effects, Error, Theme, GRID_SIZE, WINDOW_HEIGHT, WINDOW_WIDTH};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+// Side of a checker square in pixels, a few cells wide so the pattern reads
+// as a backdrop rather than a grid
+const CHECKER_SQUARE: usize = GRID_SIZE * 4;
+// How far the lighter checker squares move from the background toward the
+// border color
+const CHECKER_SHADE: f32 = 0.08;
+// Largest image accepted, the size of an 8K screen. Anything bigger only
+// wastes memory, since it is shrunk to the window anyway.
+const MAX_IMAGE_SIZE: (usize, usize) = (7680, 4320);
+
+/// What to draw beneath the board.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Background {
+    /// A single color, as 0xRRGGBB.
+    Color(u32),
+    /// Large squares alternating the theme's background color with a shade
+    /// of it, following theme changes.
+    Checker,
+    /// A picture already scaled to `WINDOW_WIDTH` x `WINDOW_HEIGHT`, row by
+    /// row. `Background::load_image` makes one from a file.
+    Image(Arc<[u32]>),
+}
+
+impl Background {
+    /// Reads a binary PPM (P6) image and stretches it to fill the window.
+    /// Most image editors can export PPM, and it needs no decoder library.
+    pub fn load_image(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(|e| Error::io(path, e))?;
+        let image = decode_ppm(&data).map_err(|message| Error::Image {
+            path: path.to_path_buf(),
+            message,
+        })?;
+        Ok(Background::Image(image.scaled_to_window().into()))
+    }
+
+    // The full-window buffer to start each frame from
+    pub(crate) fn paint(&self, theme: &Theme) -> Arc<[u32]> {
+        match self {
+            Background::Color(color) => vec![*color; WINDOW_WIDTH * WINDOW_HEIGHT].into(),
+            Background::Checker => {
+                let shade = effects::blend(theme.background, theme.border, CHECKER_SHADE);
+                (0..WINDOW_WIDTH * WINDOW_HEIGHT)
+                    .map(|i| {
+                        let (x, y) = (i % WINDOW_WIDTH, i / WINDOW_WIDTH);
+                        if (x / CHECKER_SQUARE + y / CHECKER_SQUARE).is_multiple_of(2) {
+                            theme.background
+                        } else {
+                            shade
+                        }
+                    })
+                    .collect()
+            }
+            Background::Image(pixels) => Arc::clone(pixels),
+        }
+    }
+}
+
+struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>,
+}
+
+impl Image {
+    // Nearest-neighbor stretch to the window, which keeps pixel art crisp
+    fn scaled_to_window(&self) -> Vec<u32> {
+        (0..WINDOW_WIDTH * WINDOW_HEIGHT)
+            .map(|i| {
+                let x = i % WINDOW_WIDTH * self.width / WINDOW_WIDTH;
+                let y = i / WINDOW_WIDTH * self.height / WINDOW_HEIGHT;
+                self.pixels[y * self.width + x]
+            })
+            .collect()
+    }
+}
+
+// A P6 file is the text header "P6 <width> <height> <maxval>", with optional
+// `#` comments, then a single whitespace byte and one RGB triple per pixel.
+fn decode_ppm(data: &[u8]) -> Result<Image, String> {
+    let mut rest = data;
+    let mut header = [0; 3];
+    if next_token(&mut rest) != Some(b"P6".as_slice()) {
+        return Err("not a binary PPM (P6) image".to_string());
+    }
+    for (value, name) in header.iter_mut().zip(["width", "height", "maximum value"]) {
+        *value = next_token(&mut rest)
+            .and_then(|token| std::str::from_utf8(token).ok()?.parse().ok())
+            .ok_or_else(|| format!("missing or invalid {name} in the header"))?;
+    }
+    let [width, height, max] = header;
+    if width == 0 || height == 0 {
+        return Err("the image is empty".to_string());
+    }
+    if width > MAX_IMAGE_SIZE.0 || height > MAX_IMAGE_SIZE.1 {
+        return Err(format!(
+            "the image is {width}x{height}, but can be at most {}x{}",
+            MAX_IMAGE_SIZE.0, MAX_IMAGE_SIZE.1
+        ));
+    }
+    if !(1..=255).contains(&max) {
+        return Err(format!(
+            "maximum value {max} is unsupported; save with 8 bits per channel"
+        ));
+    }
+
+    // Skip the one whitespace byte that ends the header
+    let body = rest.get(1..).unwrap_or_default();
+    let expected = width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(3))
+        .ok_or_else(|| format!("the image size {width}x{height} is too large"))?;
+    if body.len() < expected {
+        return Err(format!(
+            "expected {expected} bytes of pixels for {width}x{height}, found {}",
+            body.len()
+        ));
+    }
+    let channel = |byte: u8| (usize::from(byte) * 255 / max) as u32;
+    let pixels = body[..expected]
+        .chunks_exact(3)
+        .map(|rgb| (channel(rgb[0]) << 16) | (channel(rgb[1]) << 8) | channel(rgb[2]))
+        .collect();
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+// Split the next whitespace-separated token off the header, skipping comments.
+// Leaves `rest` starting at the byte just after the token.
+fn next_token<'a>(rest: &mut &'a [u8]) -> Option<&'a [u8]> {
+    loop {
+        let start = rest.iter().position(|b| !b.is_ascii_whitespace())?;
+        *rest = &rest[start..];
+        if rest[0] != b'#' {
+            break;
+        }
+        let end = rest.iter().position(|b| *b == b'\n').unwrap_or(rest.len());
+        *rest = &rest[end..];
+    }
+    let end = rest
+        .iter()
+        .position(|b| b.is_ascii_whitespace())
+        .unwrap_or(rest.len());
+    let (token, remainder) = rest.split_at(end);
+    *rest = remainder;
+    Some(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_pixels_after_a_commented_header() {
+        let mut data = b"P6\n# made by hand\n2 1\n255\n".to_vec();
+        data.extend([0xFF, 0x00, 0x00, 0x12, 0x34, 0x56]);
+        let image = decode_ppm(&data).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.pixels, vec![0xFF0000, 0x123456]);
+    }
+
+    #[test]
+    fn scales_channels_to_eight_bits() {
+        let mut data = b"P6 1 1 15 ".to_vec();
+        data.extend([15, 0, 5]);
+        assert_eq!(decode_ppm(&data).unwrap().pixels, vec![0xFF0055]);
+    }
+
+    #[test]
+    fn rejects_malformed_images() {
+        let cases: [&[u8]; 5] = [
+            b"P3 1 1 255 0 0 0",
+            b"P6 1 255 ",
+            b"P6 0 1 255 ",
+            b"P6 1 1 65535 ",
+            b"P6 2 2 255 \x00\x00\x00",
+        ];
+        for data in cases {
+            assert!(
+                decode_ppm(data).is_err(),
+                "{:?}",
+                String::from_utf8_lossy(data)
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_oversized_headers() {
+        let cases: [&[u8]; 3] = [
+            b"P6 9999999999 9999999999 255 ",
+            b"P6 18446744073709551615 2 255 ",
+            b"P6 7681 1 255 ",
+        ];
+        for data in cases {
+            assert!(
+                decode_ppm(data).is_err(),
+                "{:?}",
+                String::from_utf8_lossy(data)
+            );
+        }
+    }
+
+    #[test]
+    fn load_image_reports_a_missing_file() {
+        let error = Background::load_image("no/such/image.ppm").unwrap_err();
+        assert!(matches!(error, Error::Io { .. }));
+    }
+}
@@ -0,0 +1,120 @@
+// Post-processing passes applied to a finished frame before it is shown.
+
+/// Blends every pixel toward `color`. A `strength` of 0.0 leaves the frame
+/// untouched and 1.0 replaces it with `color` entirely.
+pub fn flash(buffer: &mut [u32], color: u32, strength: f32) {
+    let strength = strength.clamp(0.0, 1.0);
+    for pixel in buffer.iter_mut() {
+        *pixel = blend(*pixel, color, strength);
+    }
+}
+
+/// Darkens every pixel by `alpha`, so text drawn afterwards stands out while
+/// the frame stays visible behind it. An `alpha` of 0.0 leaves the frame
+/// untouched and 1.0 turns it black.
+pub fn draw_overlay(buffer: &mut [u32], alpha: f32) {
+    let keep = fixed_point(1.0 - alpha.clamp(0.0, 1.0));
+    for pixel in buffer.iter_mut() {
+        *pixel = scale(*pixel, keep);
+    }
+}
+
+// Brightness of the darker rows between scanlines
+const SCANLINE_BRIGHTNESS: f32 = 0.82;
+// How much the vignette darkens the edges along each axis; the corners get
+// both, but stay bright enough for the HUD to read
+const VIGNETTE_STRENGTH: f32 = 0.08;
+
+/// A retro CRT look: faint horizontal scanlines on every other row and a
+/// slight darkening toward the edges of the frame. The brightness of each
+/// row and column is worked out once up front, so applying it costs one
+/// multiply per pixel.
+#[derive(Debug)]
+pub struct Crt {
+    width: usize,
+    rows: Vec<u32>,
+    columns: Vec<u32>,
+}
+
+impl Crt {
+    /// Precomputes the pass for frames `width` x `height` pixels.
+    pub fn new(width: usize, height: usize) -> Self {
+        let rows = (0..height)
+            .map(|y| {
+                let scanline = if y % 2 == 1 { SCANLINE_BRIGHTNESS } else { 1.0 };
+                fixed_point(scanline * vignette(y, height))
+            })
+            .collect();
+        let columns = (0..width)
+            .map(|x| fixed_point(vignette(x, width)))
+            .collect();
+        Crt {
+            width,
+            rows,
+            columns,
+        }
+    }
+
+    /// Darkens `buffer` in place.
+    pub fn apply(&self, buffer: &mut [u32]) {
+        for (y, row) in buffer.chunks_exact_mut(self.width).enumerate() {
+            let row_brightness = self.rows[y];
+            for (pixel, column_brightness) in row.iter_mut().zip(&self.columns) {
+                *pixel = scale(*pixel, (row_brightness * column_brightness) >> 8);
+            }
+        }
+    }
+}
+
+// Brightness at `i` of `len` pixels along one axis: 1.0 in the middle,
+// falling off with the square of the distance to reach the edge
+fn vignette(i: usize, len: usize) -> f32 {
+    let t = (i as f32 + 0.5) / len as f32 * 2.0 - 1.0;
+    1.0 - VIGNETTE_STRENGTH * t * t
+}
+
+// A brightness in 0.0..=1.0 as fixed point out of 256
+fn fixed_point(brightness: f32) -> u32 {
+    (brightness * 256.0).round() as u32
+}
+
+// Multiplies each channel by `keep`/256, red and blue together in one go
+fn scale(pixel: u32, keep: u32) -> u32 {
+    let red_blue = (((pixel & 0xFF00FF) * keep) >> 8) & 0xFF00FF;
+    let green = (((pixel & 0x00FF00) * keep) >> 8) & 0x00FF00;
+    red_blue | green
+}
+
+/// Shifts the whole frame by `(dx, dy)` pixels, filling the uncovered edge
+/// with `fill`. Pixels shifted past the edge of the buffer are dropped.
+pub fn shake(buffer: &mut [u32], width: usize, dx: isize, dy: isize, fill: u32) {
+    if dx == 0 && dy == 0 {
+        return;
+    }
+
+    let height = buffer.len() / width;
+    let source = buffer.to_vec();
+    for y in 0..height {
+        for x in 0..width {
+            let source_x = x as isize - dx;
+            let source_y = y as isize - dy;
+            buffer[y * width + x] = if (0..width as isize).contains(&source_x)
+                && (0..height as isize).contains(&source_y)
+            {
+                source[source_y as usize * width + source_x as usize]
+            } else {
+                fill
+            };
+        }
+    }
+}
+
+// Mixes `amount` of `to` into `from`, channel by channel
+pub(crate) fn blend(from: u32, to: u32, amount: f32) -> u32 {
+    let channel = |shift: u32| {
+        let a = ((from >> shift) & 0xFF) as f32;
+        let b = ((to >> shift) & 0xFF) as f32;
+        ((a + (b - a) * amount).round() as u32) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
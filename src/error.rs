@@ -0,0 +1,110 @@
+// The error type for everything in the library that can fail: reading and
+// writing saved state, and checking a game's options.
+
+use std::io;
+use std::path::PathBuf;
+
+/// Something that went wrong loading, saving or setting up a game.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// A file couldn't be read or written.
+    #[error("{}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    /// There is no home or data directory to keep saved state in.
+    #[error("no home or data directory found")]
+    NoDataDir,
+    /// A saved file didn't contain what was expected.
+    #[error("{}, line {line}: {message}", path.display())]
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    /// A saved file was written by a newer version of the game.
+    #[error("{} is version {found}, but this build only reads up to version {supported}", path.display())]
+    VersionMismatch {
+        path: PathBuf,
+        found: u32,
+        supported: u32,
+    },
+    /// A map file was read but doesn't describe a playable level.
+    #[error("{}: {message}", path.display())]
+    Map { path: PathBuf, message: String },
+    /// An image file couldn't be decoded.
+    #[error("{}: {message}", path.display())]
+    Image { path: PathBuf, message: String },
+    /// The options for a game don't fit together.
+    #[error("{0}")]
+    Validation(String),
+}
+
+impl Error {
+    pub(crate) fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Error::Io {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_name_the_file_involved() {
+        let path = PathBuf::from("saves/config");
+        let cases = [
+            (
+                Error::io(&path, io::Error::new(io::ErrorKind::NotFound, "not found")),
+                "saves/config: not found",
+            ),
+            (Error::NoDataDir, "no home or data directory found"),
+            (
+                Error::Parse {
+                    path: path.clone(),
+                    line: 3,
+                    message: "invalid value".to_string(),
+                },
+                "saves/config, line 3: invalid value",
+            ),
+            (
+                Error::VersionMismatch {
+                    path: path.clone(),
+                    found: 2,
+                    supported: 1,
+                },
+                "saves/config is version 2, but this build only reads up to version 1",
+            ),
+            (
+                Error::Map {
+                    path: path.clone(),
+                    message: "no start".to_string(),
+                },
+                "saves/config: no start",
+            ),
+            (
+                Error::Image {
+                    path: path.clone(),
+                    message: "not a PPM".to_string(),
+                },
+                "saves/config: not a PPM",
+            ),
+            (Error::Validation("bad options".to_string()), "bad options"),
+        ];
+        for (error, message) in cases {
+            assert_eq!(error.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn io_errors_keep_their_source() {
+        let error = Error::io("config", io::Error::other("disk full"));
+        let source = std::error::Error::source(&error).unwrap();
+        assert_eq!(source.to_string(), "disk full");
+    }
+}
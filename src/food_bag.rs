@@ -0,0 +1,94 @@
+// Picks the kind of each new piece of food from a shuffle-bag: one of every
+// kind in the configured mix goes into the bag per count, the bag is
+// shuffled, and kinds are drawn without replacement until it's empty, when
+// it is refilled. Every run of a full bag has exactly the configured counts,
+// so rare kinds can't go missing for long and common ones can't streak.
+
+use crate::FoodKind;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+#[derive(Clone, Debug)]
+pub(crate) struct FoodBag {
+    mix: Vec<(FoodKind, usize)>,
+    bag: Vec<FoodKind>,
+}
+
+impl FoodBag {
+    pub(crate) fn new(mix: &[(FoodKind, usize)]) -> Self {
+        FoodBag {
+            mix: mix.to_vec(),
+            bag: Vec::new(),
+        }
+    }
+
+    // Whether `kind` ever comes out of the bag
+    pub(crate) fn contains(&self, kind: FoodKind) -> bool {
+        self.mix.iter().any(|(k, count)| *k == kind && *count > 0)
+    }
+
+    // Throw out what's left so the next draw starts a fresh bag
+    pub(crate) fn empty(&mut self) {
+        self.bag.clear();
+    }
+
+    // The next kind, refilling and reshuffling the bag first if it's empty.
+    // A mix with no counts at all only ever yields normal food.
+    pub(crate) fn draw(&mut self, rng: &mut impl Rng) -> FoodKind {
+        if self.bag.is_empty() {
+            for (kind, count) in &self.mix {
+                self.bag.extend(std::iter::repeat_n(*kind, *count));
+            }
+            self.bag.shuffle(rng);
+        }
+        self.bag.pop().unwrap_or(FoodKind::Normal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn count(kinds: &[FoodKind], kind: FoodKind) -> usize {
+        kinds.iter().filter(|k| **k == kind).count()
+    }
+
+    #[test]
+    fn every_full_bag_holds_the_configured_counts() {
+        let mix = [
+            (FoodKind::Normal, 5),
+            (FoodKind::Ghost, 2),
+            (FoodKind::Split, 1),
+        ];
+        let mut bag = FoodBag::new(&mix);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..10 {
+            let cycle: Vec<FoodKind> = (0..8).map(|_| bag.draw(&mut rng)).collect();
+            for (kind, expected) in mix {
+                assert_eq!(count(&cycle, kind), expected, "{kind:?} in {cycle:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn emptying_starts_a_fresh_bag() {
+        let mix = [(FoodKind::Normal, 1), (FoodKind::Ghost, 1)];
+        let mut bag = FoodBag::new(&mix);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        bag.draw(&mut rng);
+        bag.empty();
+        let cycle = [bag.draw(&mut rng), bag.draw(&mut rng)];
+        assert_eq!(count(&cycle, FoodKind::Normal), 1);
+        assert_eq!(count(&cycle, FoodKind::Ghost), 1);
+    }
+
+    #[test]
+    fn a_mix_without_counts_yields_normal_food() {
+        let mut bag = FoodBag::new(&[(FoodKind::Ghost, 0)]);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        assert!(!bag.contains(FoodKind::Ghost));
+        assert_eq!(bag.draw(&mut rng), FoodKind::Normal);
+    }
+}
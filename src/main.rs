@@ -1,340 +1,1086 @@
-use clap::Parser;
-use minifb::{Key, Window, WindowOptions};
-use rand::seq::IndexedRandom;
+use clap::{ArgGroup, CommandFactory, Parser, ValueEnum};
+use minifb::{Key, KeyRepeat, ScaleMode, Window, WindowOptions};
 use rand::Rng;
+use rusty_snake::{
+    effects, storage, AutopilotStrategy, Background, Board, BorderStyle, Direction, Drift, Error,
+    FoodKind, FoodValue, Game, GameConfig, GameEvent, KeyBindings, Map, Position, RestartSeed,
+    ReverseRule, ScoringZone, SpawnStrategy, Theme, TimedEvent, WinCondition, GRID_HEIGHT,
+    GRID_WIDTH, MIN_BOARD_SIZE, MIN_REFRESH_RATE, SLOW_REFRESH_RATE, WINDOW_HEIGHT, WINDOW_WIDTH,
+};
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::time::{Duration, Instant};
 
-const WINDOW_WIDTH: usize = 1280;
-const WINDOW_HEIGHT: usize = 720;
-const GRID_SIZE: usize = 20;
-const GRID_WIDTH: usize = WINDOW_WIDTH / GRID_SIZE;
-const GRID_HEIGHT: usize = WINDOW_HEIGHT / GRID_SIZE;
-
 #[derive(Parser)]
 #[command(name = "rusty-snake")]
 #[command(about = "A classic Snake game implementation in Rust")]
 #[command(version)]
+// Options that have the autopilot play, which --autopilot-strategy tunes
+#[command(group(ArgGroup::new("autopilot_play").args(["autopilot", "bench_steps"]).multiple(true)))]
 struct Cli {
-    /// Refresh rate in milliseconds (lower = faster game)
-    #[arg(long, default_value = "150")]
-    refresh_rate: u64,
+    /// Refresh rate in milliseconds (lower = faster game, minimum 16).
+    /// Defaults to the speed last set on the options screen, or 150
+    #[arg(long)]
+    refresh_rate: Option<u64>,
+
+    /// Experimental: refresh rate in milliseconds for up and down moves,
+    /// leaving --refresh-rate for left and right ones
+    #[arg(long)]
+    vertical_refresh_rate: Option<u64>,
+
+    /// Board width in cells, including walls (the camera follows the snake on large boards)
+    #[arg(long, default_value_t = GRID_WIDTH, value_parser = parse_board_size)]
+    grid_width: usize,
+
+    /// Board height in cells, including walls
+    #[arg(long, default_value_t = GRID_HEIGHT, value_parser = parse_board_size)]
+    grid_height: usize,
+
+    /// Column the snake starts in, counting the left wall as 0 (defaults to the center)
+    #[arg(long)]
+    start_x: Option<usize>,
+
+    /// Row the snake starts in, counting the top wall as 0 (defaults to the center)
+    #[arg(long)]
+    start_y: Option<usize>,
+
+    /// Play a level drawn in a text file: `#` or `X` for walls, `.` for
+    /// floor, `S` for the start and `F` for food. Sets the board size, start
+    /// and starting food
+    #[arg(
+        long,
+        value_parser = parse_map,
+        conflicts_with_all = [
+            "grid_width",
+            "grid_height",
+            "start_x",
+            "start_y",
+            "experimental_adaptive_grid",
+            "invincible",
+        ]
+    )]
+    map: Option<Map>,
+
+    /// Hold the snake for one tick before it runs into a wall, giving one
+    /// last chance to turn away (also on when turned on in the options screen)
+    #[arg(long)]
+    wall_grace: bool,
+
+    /// Tint the cells next to the head that would kill the snake, to learn
+    /// what to avoid (H toggles this while playing)
+    #[arg(long)]
+    danger_hints: bool,
+
+    /// Draw the tail stretching into place after the snake grows, rather
+    /// than appearing whole
+    #[arg(long)]
+    animate_growth: bool,
+
+    /// Throw a burst of sparks out of each pellet as it is eaten
+    #[arg(long)]
+    particles: bool,
+
+    /// Key that starts a new game after game over (a letter, digit, F1-F12,
+    /// or a name such as space, tab or home)
+    #[arg(long, default_value = "r", value_parser = parse_key)]
+    restart_key: Key,
+
+    /// Key that opens and closes the pause menu
+    #[arg(long, default_value = "p", value_parser = parse_key)]
+    pause_key: Key,
+
+    /// Key that closes the game (Q also quits unless it is bound to something else)
+    #[arg(long, default_value = "escape", value_parser = parse_key)]
+    quit_key: Key,
+
+    /// Key that leaves the game for the title screen
+    #[arg(long, default_value = "home", value_parser = parse_key)]
+    menu_key: Key,
+
+    /// Experimental: grow the board outward as the snake fills it
+    #[arg(long)]
+    experimental_adaptive_grid: bool,
+
+    /// Swap the Left and Right controls
+    #[arg(long)]
+    mirror_controls: bool,
+
+    /// Also swap the Up and Down controls (use with --mirror-controls)
+    #[arg(long, requires = "mirror_controls")]
+    mirror_vertical: bool,
+
+    /// Whether pressing the opposite direction is ignored or turns the snake into its neck
+    #[arg(long, value_enum, default_value_t = ReverseRule::Ignore)]
+    reverse_rule: ReverseRule,
+
+    /// End the game as a win once the score reaches this value
+    #[arg(long, conflicts_with = "target_length")]
+    target_score: Option<u32>,
+
+    /// End the game as a win once the snake is this many cells long
+    #[arg(long)]
+    target_length: Option<usize>,
+
+    /// Let the computer play
+    #[arg(long)]
+    autopilot: bool,
+
+    /// How the autopilot chooses its moves (with --autopilot or --bench-steps)
+    #[arg(long, value_enum, default_value_t = AutopilotStrategy::Safe, requires = "autopilot_play")]
+    autopilot_strategy: AutopilotStrategy,
+
+    /// Open on a demo of the autopilot playing behind the title, until any
+    /// key starts a game
+    #[arg(long, conflicts_with_all = ["debug_step", "headless", "bench_steps"])]
+    attract: bool,
+
+    /// Start a new game this many seconds after each game ends, for
+    /// unattended displays (R still restarts right away)
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    auto_restart: Option<Duration>,
+
+    /// Seed for food placement, to replay a particular board
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Whether restarting with R keeps the current seed or picks a new one
+    #[arg(long, value_enum, default_value_t = RestartSeed::New)]
+    restart_seed: RestartSeed,
+
+    /// Pick the snake, food, and border colors from the seed
+    #[arg(long)]
+    random_colors: bool,
+
+    /// Development aid: ignore wall and self collisions
+    #[arg(long, hide = true)]
+    invincible: bool,
+
+    /// Flash the screen red and shake it when the snake dies
+    #[arg(long)]
+    juice: bool,
+
+    /// Blink the food on a fixed real-time cadence so it's easy to spot
+    #[arg(long)]
+    blink_food: bool,
+
+    /// Draw the wall as a solid frame or a dashed or checkered pattern
+    #[arg(long, value_enum, default_value_t = BorderStyle::Solid)]
+    border_style: BorderStyle,
+
+    /// Draw beneath the board: a hex color like 1a1a2e, "checker" for a
+    /// checkerboard in the theme's colors, or the path to a PPM (P6) image,
+    /// which is stretched to fill the window
+    #[arg(long, value_parser = parse_background)]
+    background: Option<Background>,
+
+    /// Draw with faint scanlines and darkened edges, like an old CRT screen
+    #[arg(long)]
+    crt: bool,
+
+    /// Drive around with no food, growth, or score
+    #[arg(long, conflicts_with_all = ["target_score", "target_length"])]
+    zen: bool,
+
+    /// Score to start each game with
+    #[arg(long, default_value_t = 0, conflicts_with = "zen")]
+    start_score: u32,
+
+    /// Points scored for every tick the snake survives, whether or not it eats
+    #[arg(long, default_value_t = 0, conflicts_with = "zen")]
+    survival_points: u32,
+
+    /// Points a pellet is worth when it first appears
+    #[arg(long, default_value_t = 10)]
+    food_points: u32,
+
+    /// Points a pellet loses for every tick it goes uneaten
+    #[arg(long, default_value_t = 0)]
+    food_decay: u32,
+
+    /// Fewest points a pellet can decay to
+    #[arg(long, default_value_t = 0)]
+    food_min_points: u32,
+
+    /// Make one pellet in every N a ghost pellet, which lets the snake pass
+    /// through itself for a while
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), conflicts_with = "zen")]
+    ghost_every: Option<u32>,
+
+    /// How many ticks a ghost pellet lasts
+    #[arg(long, default_value_t = 20, requires = "ghost_every")]
+    ghost_ticks: u64,
+
+    /// Make one pellet in every N a split pellet, which cuts the snake in half
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), conflicts_with = "zen")]
+    split_every: Option<u32>,
+
+    /// Most pellets on the board at once
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..), conflicts_with = "zen")]
+    max_food: u64,
+
+    /// Ticks between new pellets while the board has fewer than --max-food
+    /// (0 replaces eaten pellets straight away)
+    #[arg(long, default_value_t = 0, conflicts_with = "zen")]
+    food_regen: u64,
+
+    /// Number the pellets and make them be eaten in order, oldest first;
+    /// eating the wrong one ends the run (needs --max-food 2 or more)
+    #[arg(long, conflicts_with = "zen")]
+    ordered_food: bool,
+
+    /// Add a bonus zone this many cells across that moves around the board
+    #[arg(long, conflicts_with = "zen")]
+    zone_size: Option<usize>,
+
+    /// Ticks between moves of the bonus zone
+    #[arg(long, default_value_t = 50, requires = "zone_size")]
+    zone_shift: u64,
+
+    /// Points for crossing into the bonus zone
+    #[arg(long, default_value_t = 25, requires = "zone_size")]
+    zone_bonus: u32,
+
+    /// Experimental: a current that turns the snake this way whenever it
+    /// goes --drift-every ticks without being steered
+    #[arg(long, value_enum)]
+    drift: Option<Direction>,
+
+    /// Ticks between pushes of the --drift current
+    #[arg(long, default_value_t = 5, requires = "drift", value_parser = clap::value_parser!(u64).range(1..))]
+    drift_every: u64,
+
+    /// Where food is allowed to appear
+    #[arg(long, value_enum, default_value_t = SpawnStrategy::Uniform, conflicts_with = "zen")]
+    spawn_strategy: SpawnStrategy,
+
+    /// Never spawn food right next to the snake's tail, where it could be
+    /// grabbed for free as the tail moves away
+    #[arg(long)]
+    food_avoids_tail: bool,
+
+    /// Cells the snake grows by over its first moves, without eating
+    #[arg(long, default_value_t = 0, conflicts_with = "zen")]
+    warmup_grow: usize,
+
+    /// Don't load or save the high score
+    #[arg(long)]
+    no_high_score: bool,
+
+    /// Pause the clock and step once per Space press, printing each step
+    #[arg(long)]
+    debug_step: bool,
+
+    /// Run without a window, reading U, D, L, R or step from stdin one per
+    /// line and printing the board after each
+    #[arg(long, conflicts_with_all = ["debug_step", "autopilot"])]
+    headless: bool,
+
+    /// Time N steps of an autopilot game with no window, print steps per
+    /// second and exit (seeded with --seed, or 0)
+    #[arg(long, conflicts_with_all = ["headless", "debug_step"])]
+    bench_steps: Option<u64>,
+
+    /// Warn on stderr when updating and drawing a frame takes longer than
+    /// this many milliseconds (16, one frame at 60 FPS, if no value is
+    /// given). Warnings are printed at most once a second
+    #[arg(
+        long,
+        value_name = "MS",
+        num_args = 0..=1,
+        default_missing_value = "16",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    warn_slow_frames: Option<u64>,
+
+    /// Open the window at the default size and let the system place it,
+    /// ignoring where it was last time
+    #[arg(long)]
+    reset_window: bool,
+
+    /// Print the names of the built-in color themes and exit
+    #[arg(long)]
+    list_themes: bool,
+
+    /// Print the choices for every option that takes a mode and exit
+    #[arg(long)]
+    list_modes: bool,
+
+    /// Check a map file for problems, print them, and exit without playing
+    /// (exits with an error status if there are any)
+    #[arg(long, value_name = "PATH")]
+    validate_map: Option<PathBuf>,
 }
 
-#[derive(Clone, Copy, PartialEq)]
-struct Position {
-    x: usize,
-    y: usize,
+const JUICE_DURATION: Duration = Duration::from_millis(400);
+const SHAKE_AMPLITUDE: f32 = 12.0;
+const MAX_FAILED_FRAMES: u32 = 30;
+const SLOW_FRAME_WARNING_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_REFRESH_RATE: u64 = 150;
+const FOOD_BLINK_INTERVAL: Duration = Duration::from_millis(300);
+const DEATH_FLASH: u32 = 0xFF0000;
+const HIGH_SCORE_FLASH: u32 = 0xFFD700;
+// Bounds for a remembered window size, so a corrupt or stale config can't
+// open a window too small to use or larger than any screen
+const MIN_WINDOW_SIZE: (usize, usize) = (320, 180);
+const MAX_WINDOW_SIZE: (usize, usize) = (7680, 4320);
+// Remembered positions further than this from the origin are assumed to be
+// garbage rather than a far-off monitor
+const MAX_WINDOW_OFFSET: isize = 16384;
+
+fn parse_board_size(value: &str) -> Result<usize, String> {
+    let size: usize = value.parse().map_err(|e| format!("{}", e))?;
+    if size < MIN_BOARD_SIZE {
+        return Err(format!("must be at least {}", MIN_BOARD_SIZE));
+    }
+    Ok(size)
 }
 
-#[derive(Clone, Copy)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    let seconds: f64 = value
+        .parse()
+        .map_err(|e: std::num::ParseFloatError| e.to_string())?;
+    Duration::try_from_secs_f64(seconds).map_err(|e| e.to_string())
 }
 
-struct Snake {
-    body: Vec<Position>,
-    direction: Direction,
-    growing: bool,
+fn parse_map(value: &str) -> Result<Map, String> {
+    Map::load(value).map_err(|e| e.to_string())
 }
 
-impl Snake {
-    fn new() -> Self {
-        Snake {
-            body: vec![Position {
-                x: GRID_WIDTH / 2,
-                y: GRID_HEIGHT / 2,
-            }],
-            direction: Direction::Right,
-            growing: false,
+fn parse_background(value: &str) -> Result<Background, String> {
+    if value == "checker" {
+        return Ok(Background::Checker);
+    }
+    let hex = value.trim_start_matches('#');
+    if hex.len() == 6 {
+        if let Ok(color) = u32::from_str_radix(hex, 16) {
+            return Ok(Background::Color(color));
         }
     }
+    Background::load_image(value).map_err(|e| e.to_string())
+}
 
-    fn update(&mut self) {
-        // Get current head position
-        let head = self.body[0];
-
-        // Calculate new head position
-        let new_head = match self.direction {
-            Direction::Up => Position {
-                x: head.x,
-                y: head.y.saturating_sub(1),
-            },
-            Direction::Down => Position {
-                x: head.x,
-                y: (head.y + 1).min(GRID_HEIGHT - 1),
-            },
-            Direction::Left => Position {
-                x: head.x.saturating_sub(1),
-                y: head.y,
-            },
-            Direction::Right => Position {
-                x: (head.x + 1).min(GRID_WIDTH - 1),
-                y: head.y,
-            },
-        };
+// Shake the frame and tint it `color`, both fading out over JUICE_DURATION
+fn apply_death_juice(buffer: &mut [u32], since_death: Duration, color: u32) {
+    let remaining = 1.0 - since_death.as_secs_f32() / JUICE_DURATION.as_secs_f32();
+    let amplitude = (SHAKE_AMPLITUDE * remaining) as i32;
+    let mut rng = rand::rng();
+    let dx = rng.random_range(-amplitude..=amplitude) as isize;
+    let dy = rng.random_range(-amplitude..=amplitude) as isize;
+    effects::shake(buffer, WINDOW_WIDTH, dx, dy, 0x000000);
+    effects::flash(buffer, color, 0.5 * remaining);
+}
 
-        // Add new head
-        self.body.insert(0, new_head);
+// The shuffle-bag mix for the special pellets asked for. A bag as long as the
+// least common multiple of the `--*-every` values holds the right share of
+// each kind, with normal pellets making up the rest.
+fn food_mix(cli: &Cli) -> Result<Vec<(FoodKind, usize)>, String> {
+    let specials: Vec<(FoodKind, usize)> = [
+        (FoodKind::Ghost, cli.ghost_every),
+        (FoodKind::Split, cli.split_every),
+    ]
+    .into_iter()
+    .filter_map(|(kind, every)| every.map(|every| (kind, every as usize)))
+    .collect();
+    let bag = specials.iter().fold(1, |bag, (_, every)| lcm(bag, *every));
 
-        // Remove tail if not growing
-        if !self.growing {
-            self.body.pop();
-        } else {
-            self.growing = false;
-        }
+    let mut mix: Vec<(FoodKind, usize)> = specials
+        .iter()
+        .map(|(kind, every)| (*kind, bag / every))
+        .collect();
+    let special: usize = mix.iter().map(|(_, count)| count).sum();
+    if special > bag {
+        return Err(
+            "--ghost-every and --split-every together ask for more than every pellet".into(),
+        );
     }
+    mix.push((FoodKind::Normal, bag - special));
+    Ok(mix)
+}
 
-    fn change_direction(&mut self, new_direction: Direction) {
-        // Prevent the snake from going backwards into itself
-        match (self.direction, new_direction) {
-            (Direction::Up, Direction::Down)
-            | (Direction::Down, Direction::Up)
-            | (Direction::Left, Direction::Right)
-            | (Direction::Right, Direction::Left) => return,
-            _ => self.direction = new_direction,
-        }
+fn lcm(a: usize, b: usize) -> usize {
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        (x, y) = (y, x % y);
     }
+    a / x * b
+}
 
-    fn grow(&mut self) {
-        self.growing = true;
+fn load_high_score() -> u32 {
+    storage::load_high_score().unwrap_or_else(|e| {
+        eprintln!("Warning: unable to load high score: {}", e);
+        0
+    })
+}
+
+// Scores are only comparable when they were earned under the default
+// scoring rules, by a player, in a game that can be lost
+fn tracks_high_score(cli: &Cli) -> bool {
+    !(cli.no_high_score
+        || cli.autopilot
+        || cli.invincible
+        || cli.zen
+        || cli.debug_step
+        || cli.headless
+        || cli.bench_steps.is_some()
+        || cli.start_score > 0
+        || cli.survival_points > 0
+        || cli.food_points != FoodValue::default().base
+        || cli.food_decay > 0
+        || cli.food_min_points > 0
+        || cli.zone_size.is_some())
+}
+
+// Step once when Space is pressed and dump what happened to stdout
+fn debug_step(game: &mut Game, window: &Window) -> Vec<TimedEvent> {
+    if game.is_paused() || !window.is_key_pressed(Key::Space, KeyRepeat::No) {
+        return Vec::new();
     }
+    let events = game.step();
+    print_step(game, &events);
+    events
+}
+
+// The events of the step just taken, followed by the board
+fn print_step(game: &Game, events: &[TimedEvent]) {
+    let events: Vec<GameEvent> = events.iter().map(|timed| timed.event).collect();
+    println!("Tick {}: {:?}", game.ticks(), events);
+    print!("{}", game.to_ascii());
+}
 
-    fn check_collision(&self) -> bool {
-        let head = self.body[0];
+fn happened(events: &[TimedEvent], event: GameEvent) -> bool {
+    events.iter().any(|timed| timed.event == event)
+}
 
-        // Check if head hits the walls
-        if head.x == 0 || head.x >= GRID_WIDTH - 1 || head.y == 0 || head.y >= GRID_HEIGHT - 1 {
-            return true;
+// Drive the game from stdin, one command per line: U, D, L or R turns the
+// snake and steps it, and `step` steps it without turning. The board is
+// printed after every step, and the final stats once stdin runs out.
+fn run_headless(game: &mut Game) -> ExitCode {
+    print!("{}", game.to_ascii());
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("Error: unable to read stdin: {}", e);
+                return ExitCode::FAILURE;
+            }
+        };
+        let command = line.trim();
+        let direction = match command.to_ascii_lowercase().as_str() {
+            "" => continue,
+            "u" => Some(Direction::Up),
+            "d" => Some(Direction::Down),
+            "l" => Some(Direction::Left),
+            "r" => Some(Direction::Right),
+            "step" => None,
+            _ => {
+                eprintln!("Warning: ignoring unknown command {:?}", command);
+                continue;
+            }
+        };
+        if let Some(direction) = direction {
+            game.turn(direction);
         }
+        let events = game.step();
+        print_step(game, &events);
+    }
+
+    let outcome = if game.is_won() {
+        "won"
+    } else if game.is_game_over() {
+        "died"
+    } else {
+        "running"
+    };
+    println!(
+        "Score: {}  Length: {}  Ticks: {}  Outcome: {}  State: {:016x}",
+        game.score(),
+        game.snake().body().len(),
+        game.ticks(),
+        outcome,
+        game.state_hash()
+    );
+    ExitCode::SUCCESS
+}
 
-        // Check if head hits the body
-        for segment in &self.body[1..] {
-            if head.x == segment.x && head.y == segment.y {
-                return true;
+fn validate_map(path: &Path) -> ExitCode {
+    match Map::check(path) {
+        Ok(map) => {
+            println!(
+                "{}: OK, {}x{} with {} floor cells and {} food",
+                path.display(),
+                map.board.width,
+                map.board.height,
+                map.board.playable_cells(),
+                map.food.len()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(problems) => {
+            for problem in &problems {
+                eprintln!("{}", problem);
             }
+            eprintln!("{} problem(s) found", problems.len());
+            ExitCode::FAILURE
         }
+    }
+}
 
-        false
+fn list_themes() {
+    for (name, _) in Theme::PRESETS {
+        println!("{}", name);
     }
 }
 
-struct Food {
-    position: Position,
+// Every mode option with its choices, read from the same enums the options
+// parse into so the list can't drift from what's accepted
+fn list_modes() {
+    print_choices::<ReverseRule>("reverse-rule");
+    print_choices::<SpawnStrategy>("spawn-strategy");
+    print_choices::<BorderStyle>("border-style");
+    print_choices::<AutopilotStrategy>("autopilot-strategy");
+    print_choices::<RestartSeed>("restart-seed");
+    print_choices::<Direction>("drift");
 }
 
-impl Food {
-    fn new() -> Self {
-        Food {
-            position: Position { x: 0, y: 0 },
+fn print_choices<T: ValueEnum>(option: &str) {
+    println!("--{}", option);
+    for value in T::value_variants().iter().filter_map(T::to_possible_value) {
+        match value.get_help() {
+            Some(help) => println!("  {:<16}{}", value.get_name(), help),
+            None => println!("  {}", value.get_name()),
         }
     }
+}
 
-    fn spawn_early_game(&mut self, snake: &Snake) {
-        let mut rng = rand::rng();
-        loop {
-            let x = rng.random_range(1..GRID_WIDTH - 1);
-            let y = rng.random_range(1..GRID_HEIGHT - 1);
-
-            // Make sure food doesn't spawn on snake
-            let mut valid = true;
-            for segment in &snake.body {
-                if segment.x == x && segment.y == y {
-                    valid = false;
-                    break;
-                }
-            }
+// The size to open the window at: the remembered one, kept within sane
+// bounds, or the native size of the frame buffer
+fn window_size(saved: &storage::Config) -> (usize, usize) {
+    saved
+        .window_size
+        .map_or((WINDOW_WIDTH, WINDOW_HEIGHT), |(width, height)| {
+            (
+                width.clamp(MIN_WINDOW_SIZE.0, MAX_WINDOW_SIZE.0),
+                height.clamp(MIN_WINDOW_SIZE.1, MAX_WINDOW_SIZE.1),
+            )
+        })
+}
 
-            if valid {
-                self.position = Position { x, y };
-                break;
-            }
+// The remembered position, unless it's implausibly far away. minifb can't
+// list monitors, so a position left over from a monitor that has since been
+// unplugged can still put the window off screen; --reset-window recovers.
+fn window_position(saved: &storage::Config) -> Option<(isize, isize)> {
+    saved
+        .window_position
+        .filter(|(x, y)| x.abs() <= MAX_WINDOW_OFFSET && y.abs() <= MAX_WINDOW_OFFSET)
+}
+
+// Step an autopilot game `steps` times as fast as possible, starting over
+// with the same config whenever it ends, and report the rate. Only the steps
+// are timed; the games are seeded, so every run does the same work.
+fn run_bench(config: GameConfig, steps: u64) {
+    let mut game = Game::new(config.clone());
+    let mut games = 1;
+    let mut best_score = 0;
+    let started = Instant::now();
+    for _ in 0..steps {
+        if game.is_game_over() || game.is_won() {
+            best_score = best_score.max(game.score());
+            game.reset_with_config(config.clone());
+            games += 1;
         }
+        game.step();
     }
+    let elapsed = started.elapsed();
+    best_score = best_score.max(game.score());
 
-    fn spawn_late_game(&mut self, snake: &Snake) {
-        let mut allowed_spawns: Vec<Position> = Vec::with_capacity(GRID_WIDTH * GRID_HEIGHT);
-        for x in 1..GRID_WIDTH - 1 {
-            for y in 1..GRID_HEIGHT - 1 {
-                let mut valid = true;
-                for segment in &snake.body {
-                    if segment.x != x && segment.y == y {
-                        valid = false;
-                        break;
-                    }
-                }
+    let rate = steps as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+    println!(
+        "{} steps in {:.3}s: {:.0} steps per second",
+        steps,
+        elapsed.as_secs_f64(),
+        rate
+    );
+    println!(
+        "Games: {}  Best score: {}  Final score: {}  Final length: {}",
+        games,
+        best_score,
+        game.score(),
+        game.snake().body().len()
+    );
+}
 
-                if valid {
-                    allowed_spawns.push(Position { x: x, y: y });
-                }
-            }
-        }
+// Clamp the refresh rate so the game never steps faster than ~60 ticks per
+// second, which would couple game speed to the frame rate.
+fn clamp_refresh_rate(refresh_rate: u64) -> u64 {
+    if refresh_rate < MIN_REFRESH_RATE {
+        eprintln!(
+            "Warning: refresh rate {}ms is too low, using {}ms",
+            refresh_rate, MIN_REFRESH_RATE
+        );
+        return MIN_REFRESH_RATE;
+    }
+    if refresh_rate > SLOW_REFRESH_RATE {
+        eprintln!(
+            "Warning: refresh rate {}ms is very slow, the game may be unplayable",
+            refresh_rate
+        );
+    }
+    refresh_rate
+}
+
+// Keys for --restart-key and friends, by the names minifb gives them
+fn parse_key(value: &str) -> Result<Key, String> {
+    const LETTERS: [Key; 26] = [
+        Key::A,
+        Key::B,
+        Key::C,
+        Key::D,
+        Key::E,
+        Key::F,
+        Key::G,
+        Key::H,
+        Key::I,
+        Key::J,
+        Key::K,
+        Key::L,
+        Key::M,
+        Key::N,
+        Key::O,
+        Key::P,
+        Key::Q,
+        Key::R,
+        Key::S,
+        Key::T,
+        Key::U,
+        Key::V,
+        Key::W,
+        Key::X,
+        Key::Y,
+        Key::Z,
+    ];
+    const DIGITS: [Key; 10] = [
+        Key::Key0,
+        Key::Key1,
+        Key::Key2,
+        Key::Key3,
+        Key::Key4,
+        Key::Key5,
+        Key::Key6,
+        Key::Key7,
+        Key::Key8,
+        Key::Key9,
+    ];
+    const FUNCTION: [Key; 12] = [
+        Key::F1,
+        Key::F2,
+        Key::F3,
+        Key::F4,
+        Key::F5,
+        Key::F6,
+        Key::F7,
+        Key::F8,
+        Key::F9,
+        Key::F10,
+        Key::F11,
+        Key::F12,
+    ];
 
-        match allowed_spawns.choose(&mut rand::rng()) {
-            Some(i) => self.position = *i,
-            None => println!("Game Won!"),
+    let name = value.to_ascii_lowercase();
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c @ 'a'..='z'), None) => return Ok(LETTERS[c as usize - 'a' as usize]),
+        (Some(c @ '0'..='9'), None) => return Ok(DIGITS[c as usize - '0' as usize]),
+        _ => {}
+    }
+    if let Some(n) = name.strip_prefix('f').and_then(|n| n.parse::<usize>().ok()) {
+        if (1..=FUNCTION.len()).contains(&n) {
+            return Ok(FUNCTION[n - 1]);
         }
     }
+    Ok(match name.as_str() {
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "space" => Key::Space,
+        "enter" => Key::Enter,
+        "escape" | "esc" => Key::Escape,
+        "backspace" => Key::Backspace,
+        "tab" => Key::Tab,
+        "delete" => Key::Delete,
+        "insert" => Key::Insert,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        _ => {
+            return Err(
+                "expected a letter, digit, F1-F12, an arrow (up, down, left, right), space, \
+                 enter, escape, backspace, tab, delete, insert, home, end, pageup or pagedown"
+                    .to_string(),
+            )
+        }
+    })
 }
 
-struct Game {
-    snake: Snake,
-    food: Food,
-    score: u32,
-    game_over: bool,
-    last_update: Instant,
-    refresh_rate: Duration,
-}
+fn main() -> ExitCode {
+    let mut cli = Cli::parse();
+    if cli.list_themes || cli.list_modes {
+        if cli.list_themes {
+            list_themes();
+        }
+        if cli.list_modes {
+            list_modes();
+        }
+        return ExitCode::SUCCESS;
+    }
+    if let Some(path) = &cli.validate_map {
+        return validate_map(path);
+    }
+    cli.vertical_refresh_rate = cli.vertical_refresh_rate.map(clamp_refresh_rate);
+
+    let win_condition = match (cli.target_score, cli.target_length) {
+        (Some(score), _) => Some(WinCondition::TargetScore(score)),
+        (None, Some(length)) => Some(WinCondition::TargetLength(length)),
+        (None, None) => None,
+    };
+
+    let (board, start, initial_food) = match cli.map.take() {
+        Some(map) => (map.board, Some(map.start), map.food),
+        None => {
+            let board = Board::new(cli.grid_width, cli.grid_height);
+            let start = (cli.start_x.is_some() || cli.start_y.is_some()).then(|| Position {
+                x: cli.start_x.unwrap_or(board.width / 2),
+                y: cli.start_y.unwrap_or(board.height / 2),
+            });
+            (board, start, Vec::new())
+        }
+    };
+
+    // A config from a newer build is left alone rather than overwritten
+    let (mut saved, config_writable) = match storage::load_config() {
+        Ok(saved) => (saved, true),
+        Err(e) => {
+            eprintln!("Warning: ignoring saved config: {}", e);
+            let writable = !matches!(e, Error::VersionMismatch { .. });
+            (storage::Config::default(), writable)
+        }
+    };
+    let theme = saved
+        .theme
+        .as_deref()
+        .and_then(Theme::preset)
+        .unwrap_or_default();
+    let refresh_rate = clamp_refresh_rate(
+        cli.refresh_rate
+            .or(saved.refresh_rate)
+            .unwrap_or(DEFAULT_REFRESH_RATE),
+    );
+
+    let config = GameConfig {
+        refresh_rate,
+        vertical_refresh_rate: cli.vertical_refresh_rate,
+        board,
+        adaptive_grid: cli.experimental_adaptive_grid,
+        mirror_horizontal: cli.mirror_controls,
+        mirror_vertical: cli.mirror_vertical,
+        reverse_rule: cli.reverse_rule,
+        wall_grace: cli.wall_grace || saved.wall_grace == Some(true),
+        danger_hints: cli.danger_hints,
+        animate_growth: cli.animate_growth,
+        particles: cli.particles,
+        key_bindings: KeyBindings {
+            restart: cli.restart_key,
+            pause: cli.pause_key,
+            quit: cli.quit_key,
+            menu: cli.menu_key,
+            ..KeyBindings::default()
+        },
+        scoring_zone: cli.zone_size.map(|size| ScoringZone {
+            size,
+            shift_ticks: cli.zone_shift,
+            bonus: cli.zone_bonus,
+        }),
+        drift: cli.drift.map(|direction| Drift {
+            direction,
+            every_ticks: cli.drift_every,
+        }),
+        win_condition,
+        autopilot: cli.autopilot.then_some(cli.autopilot_strategy),
+        attract: cli.attract,
+        auto_restart: cli.auto_restart,
+        seed: cli.seed,
+        restart_seed: cli.restart_seed,
+        random_colors: cli.random_colors,
+        theme,
+        background: cli.background.clone(),
+        border_style: cli.border_style,
+        invincible: cli.invincible,
+        zen: cli.zen,
+        start_score: cli.start_score,
+        survival_points: cli.survival_points,
+        food_value: FoodValue {
+            base: cli.food_points,
+            decay: cli.food_decay,
+            floor: cli.food_min_points,
+        },
+        food_scorer: None,
+        food_mix: food_mix(&cli).unwrap_or_else(|e| {
+            Cli::command()
+                .error(clap::error::ErrorKind::ValueValidation, e)
+                .exit()
+        }),
+        ghost_ticks: cli.ghost_ticks,
+        // Room for all of the map's food, however low --max-food is
+        max_food: (cli.max_food as usize).max(initial_food.len()),
+        ordered_food: cli.ordered_food,
+        food_regen_ticks: cli.food_regen,
+        spawn_strategy: cli.spawn_strategy,
+        food_avoids_tail: cli.food_avoids_tail,
+        // Runs the player doesn't fully control don't compete for the best
+        high_score: tracks_high_score(&cli).then(load_high_score),
+        start,
+        initial_food,
+        food_sequence: Vec::new(),
+        warmup_growth: cli.warmup_grow,
+        snake: None,
+    };
+    if let Err(e) = config.validate() {
+        Cli::command()
+            .error(clap::error::ErrorKind::ValueValidation, e)
+            .exit();
+    }
 
-impl Game {
-    fn new(refresh_rate: u64) -> Self {
-        let mut game = Game {
-            snake: Snake::new(),
-            food: Food::new(),
-            score: 0,
-            game_over: false,
-            last_update: Instant::now(),
-            refresh_rate: Duration::from_millis(refresh_rate),
+    if let Some(steps) = cli.bench_steps {
+        let config = GameConfig {
+            autopilot: Some(cli.autopilot_strategy),
+            seed: Some(cli.seed.unwrap_or(0)),
+            ..config
         };
-        game.food.spawn_early_game(&game.snake);
-        game
+        run_bench(config, steps);
+        return ExitCode::SUCCESS;
     }
 
-    fn update(&mut self) {
-        if self.game_over {
-            return;
-        }
+    // Headless runs keep stdout to the board so it can be piped
+    if cli.headless {
+        let mut game = Game::new(config);
+        eprintln!("Seed: {}", game.seed());
+        return run_headless(&mut game);
+    }
 
-        if self.last_update.elapsed() >= self.refresh_rate {
-            self.snake.update();
-            self.last_update = Instant::now();
-
-            // Check if snake ate food
-            let head = self.snake.body[0];
-            if head.x == self.food.position.x && head.y == self.food.position.y {
-                self.snake.grow();
-                self.score += 10;
-                if self.snake.body.len() > GRID_WIDTH * GRID_HEIGHT / 2 {
-                    self.food.spawn_late_game(&self.snake);
-                } else {
-                    self.food.spawn_early_game(&self.snake);
-                }
-            }
+    println!("Starting Rusty Snake with refresh rate: {}ms", refresh_rate);
+    println!("Use arrow keys to move, R to restart, ESC or Q to exit");
 
-            // Check for collisions
-            if self.snake.check_collision() {
-                self.game_over = true;
-            }
+    if cli.reset_window {
+        saved.window_position = None;
+        saved.window_size = None;
+    }
+    // The frame buffer stays WINDOW_WIDTH x WINDOW_HEIGHT whatever the window
+    // size; minifb scales it to fit, keeping its aspect ratio
+    let (width, height) = window_size(&saved);
+    let mut window = match Window::new(
+        &format!("Rusty Snake - Refresh Rate: {}ms", refresh_rate),
+        width,
+        height,
+        WindowOptions {
+            resize: true,
+            scale_mode: ScaleMode::AspectRatioStretch,
+            ..WindowOptions::default()
+        },
+    ) {
+        Ok(window) => window,
+        Err(e) => {
+            eprintln!("Error: unable to create window: {}", e);
+            return ExitCode::FAILURE;
         }
+    };
+    if let Some((x, y)) = window_position(&saved) {
+        window.set_position(x, y);
     }
+    let mut geometry = (window.get_position(), window.get_size());
 
-    fn handle_input(&mut self, window: &Window) {
-        if self.game_over {
-            if window.is_key_pressed(Key::R, minifb::KeyRepeat::No) {
-                self.restart();
-            }
-            return;
-        }
+    let mut game = Game::new(config);
+    println!("Seed: {}", game.seed());
+    let mut buffer: Vec<u32> = vec![0; WINDOW_WIDTH * WINDOW_HEIGHT];
 
-        if window.is_key_pressed(Key::Up, minifb::KeyRepeat::No) {
-            self.snake.change_direction(Direction::Up);
-        }
-        if window.is_key_pressed(Key::Down, minifb::KeyRepeat::No) {
-            self.snake.change_direction(Direction::Down);
+    let mut died_at: Option<Instant> = None;
+    let mut failed_frames = 0;
+    let frame_budget = cli.warn_slow_frames.map(Duration::from_millis);
+    let mut last_slow_warning: Option<Instant> = None;
+    let mut slow_frames = 0;
+
+    let crt = cli
+        .crt
+        .then(|| effects::Crt::new(WINDOW_WIDTH, WINDOW_HEIGHT));
+    let started = Instant::now();
+    let mut should_exit = false;
+    while !should_exit {
+        // minifb reads the keyboard at the end of `update_with_buffer`, after
+        // its frame-rate sleep, so keys are handed to the game and the step
+        // taken straight after they're read, with nothing slow in between.
+        // A turn therefore lands on the first step after the frame it was
+        // pressed in: at most one frame late (minifb's 4ms frame cap, plus
+        // about 0.25ms to render in release builds), the rest of the wait
+        // being the refresh interval itself.
+        let frame_started = Instant::now();
+        let options = game.options();
+        game.handle_input(&window);
+        let events = if cli.debug_step {
+            debug_step(&mut game, &window)
+        } else {
+            game.update()
+        };
+        // Only what changed is saved, so a one-off --refresh-rate isn't
+        // remembered just because the theme was changed in the same run
+        let changed = game.options();
+        if changed != options && config_writable {
+            if changed.theme != options.theme {
+                saved.theme = changed.theme.preset_name().map(str::to_string);
+            }
+            if changed.refresh_rate != options.refresh_rate {
+                saved.refresh_rate = Some(changed.refresh_rate);
+            }
+            if changed.wall_grace != options.wall_grace {
+                saved.wall_grace = Some(changed.wall_grace);
+            }
+            if let Err(e) = storage::save_config(&saved) {
+                eprintln!("Warning: unable to save config: {}", e);
+            }
         }
-        if window.is_key_pressed(Key::Left, minifb::KeyRepeat::No) {
-            self.snake.change_direction(Direction::Left);
+        if cli.juice && happened(&events, GameEvent::Died) && !game.is_attract() {
+            died_at = Some(Instant::now());
         }
-        if window.is_key_pressed(Key::Right, minifb::KeyRepeat::No) {
-            self.snake.change_direction(Direction::Right);
+        if game.is_new_high_score()
+            && (happened(&events, GameEvent::Died) || happened(&events, GameEvent::Won))
+        {
+            if let Err(e) = storage::save_high_score(game.score()) {
+                eprintln!("Warning: unable to save high score: {}", e);
+            }
         }
-    }
+        let food_visible = !cli.blink_food
+            || (started.elapsed().as_millis() / FOOD_BLINK_INTERVAL.as_millis()).is_multiple_of(2);
+        game.render(&mut buffer, food_visible);
 
-    fn render(&self, buffer: &mut [u32]) {
-        // Clear buffer (black)
-        for pixel in buffer.iter_mut() {
-            *pixel = 0x000000; // Black
+        if let Some(since_death) = died_at.map(|at| at.elapsed()) {
+            if since_death < JUICE_DURATION {
+                let color = if game.is_new_high_score() {
+                    HIGH_SCORE_FLASH
+                } else {
+                    DEATH_FLASH
+                };
+                apply_death_juice(&mut buffer, since_death, color);
+            } else {
+                died_at = None;
+            }
+        }
+        if let Some(crt) = &crt {
+            crt.apply(&mut buffer);
         }
 
-        // Draw snake (green)
-        for segment in &self.snake.body {
-            let start_x = segment.x * GRID_SIZE;
-            let start_y = segment.y * GRID_SIZE;
-            for y in start_y..start_y + GRID_SIZE {
-                for x in start_x..start_x + GRID_SIZE {
-                    if y < WINDOW_HEIGHT && x < WINDOW_WIDTH {
-                        buffer[y * WINDOW_WIDTH + x] = 0x00FF00; // Green
-                    }
+        // Time the frame's own work, not the frame-rate sleep in the present
+        // below. Slow frames between warnings are counted, not each printed.
+        if let Some(budget) = frame_budget {
+            let took = frame_started.elapsed();
+            if took > budget {
+                slow_frames += 1;
+                if last_slow_warning.is_none_or(|at| at.elapsed() >= SLOW_FRAME_WARNING_INTERVAL) {
+                    eprintln!(
+                        "Warning: frame took {:.1}ms, over the {}ms budget ({} slow since the last warning)",
+                        took.as_secs_f64() * 1000.0,
+                        budget.as_millis(),
+                        slow_frames
+                    );
+                    last_slow_warning = Some(Instant::now());
+                    slow_frames = 0;
                 }
             }
         }
 
-        // Draw food (red)
-        let start_x = self.food.position.x * GRID_SIZE;
-        let start_y = self.food.position.y * GRID_SIZE;
-        for y in start_y..start_y + GRID_SIZE {
-            for x in start_x..start_x + GRID_SIZE {
-                if y < WINDOW_HEIGHT && x < WINDOW_WIDTH {
-                    buffer[y * WINDOW_WIDTH + x] = 0xFF0000; // Red
+        // A failed present is usually a one-off backend hiccup, so log it and
+        // keep going, but give up once frames keep failing in a row
+        match window.update_with_buffer(&buffer, WINDOW_WIDTH, WINDOW_HEIGHT) {
+            Ok(()) => failed_frames = 0,
+            Err(e) => {
+                failed_frames += 1;
+                eprintln!("Error: unable to update window: {}", e);
+                if failed_frames >= MAX_FAILED_FRAMES {
+                    eprintln!(
+                        "Error: {} frames in a row failed to draw, exiting",
+                        failed_frames
+                    );
+                    return ExitCode::FAILURE;
                 }
             }
         }
 
-        // Draw border (white)
-        for y in 0..WINDOW_HEIGHT {
-            for x in 0..WINDOW_WIDTH {
-                if x < GRID_SIZE
-                    || x >= WINDOW_WIDTH - GRID_SIZE
-                    || y < GRID_SIZE
-                    || y >= WINDOW_HEIGHT - GRID_SIZE
-                {
-                    buffer[y * WINDOW_WIDTH + x] = 0xFFFFFF; // White
-                }
-            }
+        should_exit = !window.is_open() || game.quit_requested();
+        // Read while the window is still open, as it may already be gone by
+        // the time the loop ends
+        if window.is_open() {
+            geometry = (window.get_position(), window.get_size());
         }
     }
 
-    fn restart(&mut self) {
-        self.snake = Snake::new();
-        self.food = Food::new();
-        self.food.spawn_early_game(&self.snake);
-        self.score = 0;
-        self.game_over = false;
-        self.last_update = Instant::now();
+    let (position, size) = geometry;
+    let remembered = storage::Config {
+        window_position: Some(position),
+        window_size: Some(size),
+        ..saved.clone()
+    };
+    if remembered != saved && config_writable {
+        if let Err(e) = storage::save_config(&remembered) {
+            eprintln!("Warning: unable to save config: {}", e);
+        }
     }
+    ExitCode::SUCCESS
 }
 
-fn main() {
-    let cli = Cli::parse();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    println!(
-        "Starting Rusty Snake with refresh rate: {}ms",
-        cli.refresh_rate
-    );
-    println!("Use arrow keys to move, R to restart, ESC to exit");
-
-    let mut window = Window::new(
-        &format!("Rusty Snake - Refresh Rate: {}ms", cli.refresh_rate),
-        WINDOW_WIDTH,
-        WINDOW_HEIGHT,
-        WindowOptions::default(),
-    )
-    .unwrap_or_else(|e| {
-        panic!("Unable to create window: {}", e);
-    });
-
-    let mut game = Game::new(cli.refresh_rate);
-    let mut buffer: Vec<u32> = vec![0; WINDOW_WIDTH * WINDOW_HEIGHT];
+    #[test]
+    fn cli_definition_is_consistent() {
+        Cli::command().debug_assert();
+    }
 
-    while window.is_open() && !window.is_key_down(Key::Escape) {
-        game.handle_input(&window);
-        game.update();
-        game.render(&mut buffer);
+    #[test]
+    fn autopilot_strategy_needs_something_the_autopilot_plays() {
+        let parse = |args: &[&str]| Cli::try_parse_from(["rusty-snake"].iter().chain(args));
+        assert!(parse(&["--autopilot", "--autopilot-strategy", "greedy"]).is_ok());
+        assert!(parse(&["--bench-steps", "10", "--autopilot-strategy", "greedy"]).is_ok());
+        assert!(parse(&["--autopilot-strategy", "greedy"]).is_err());
+    }
+
+    #[test]
+    fn high_scores_are_only_tracked_under_default_scoring() {
+        let tracks = |args: &[&str]| {
+            tracks_high_score(&Cli::try_parse_from(["rusty-snake"].iter().chain(args)).unwrap())
+        };
+        assert!(tracks(&[]));
+        assert!(tracks(&["--food-points", "10"]));
+        assert!(!tracks(&["--survival-points", "1"]));
+        assert!(!tracks(&["--food-points", "20"]));
+        assert!(!tracks(&["--food-decay", "1"]));
+        assert!(!tracks(&["--food-min-points", "5"]));
+        assert!(!tracks(&["--zone-size", "4"]));
+    }
+
+    #[test]
+    fn clamp_refresh_rate_raises_low_rates_to_the_minimum() {
+        assert_eq!(clamp_refresh_rate(0), MIN_REFRESH_RATE);
+        assert_eq!(clamp_refresh_rate(MIN_REFRESH_RATE - 1), MIN_REFRESH_RATE);
+    }
 
-        window
-            .update_with_buffer(&buffer, WINDOW_WIDTH, WINDOW_HEIGHT)
-            .unwrap();
+    #[test]
+    fn clamp_refresh_rate_keeps_other_rates() {
+        assert_eq!(clamp_refresh_rate(MIN_REFRESH_RATE), MIN_REFRESH_RATE);
+        assert_eq!(clamp_refresh_rate(150), 150);
+        // Very slow rates are only warned about
+        assert_eq!(
+            clamp_refresh_rate(SLOW_REFRESH_RATE + 1),
+            SLOW_REFRESH_RATE + 1
+        );
     }
 }
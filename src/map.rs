@@ -0,0 +1,238 @@
+// Levels drawn as plain text, one character per cell:
+//
+//   #  wall          .  open floor
+//   X  obstacle      S  where the snake starts
+//   F  food
+//
+// Every row must be the same width and the outermost cells must all be
+// walls. Blank lines at the end of the file are ignored.
+
+use crate::{Board, Direction, Error, Position, MIN_BOARD_SIZE};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+
+/// A level read from a map file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Map {
+    /// The board, including any obstacles inside its border.
+    pub board: Board,
+    /// Cell the snake starts on.
+    pub start: Position,
+    /// Cells that hold food when each game starts.
+    pub food: Vec<Position>,
+}
+
+impl Map {
+    /// Reads a map file, checking that it has a closed border, exactly one
+    /// start and no floor the snake can't reach. Fails with the first
+    /// problem found; `Map::check` lists them all.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Map::check(path).map_err(|mut problems| problems.swap_remove(0))
+    }
+
+    /// Reads a map file like `Map::load`, but carries on past problems to
+    /// report every one it finds.
+    pub fn check(path: impl AsRef<Path>) -> Result<Self, Vec<Error>> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(|e| vec![Error::io(path, e)])?;
+        Map::parse(&contents, path)
+    }
+
+    // The level drawn in `contents`, read from the map file at `path`
+    fn parse(contents: &str, path: &Path) -> Result<Self, Vec<Error>> {
+        let parse_error = |line: usize, message: String| Error::Parse {
+            path: path.to_path_buf(),
+            line,
+            message,
+        };
+        let map_error = |message: String| Error::Map {
+            path: path.to_path_buf(),
+            message,
+        };
+
+        let rows: Vec<&str> = contents.trim_end().lines().collect();
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        let height = rows.len();
+        if width < MIN_BOARD_SIZE || height < MIN_BOARD_SIZE {
+            return Err(vec![map_error(format!(
+                "the map is {}x{}, but needs to be at least {}x{}",
+                width, height, MIN_BOARD_SIZE, MIN_BOARD_SIZE
+            ))]);
+        }
+
+        let mut problems = Vec::new();
+        let mut walls = Vec::new();
+        let mut starts = Vec::new();
+        let mut food = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            let line = y + 1;
+            if row.chars().count() != width {
+                problems.push(parse_error(
+                    line,
+                    format!(
+                        "row is {} cells wide, but the first row is {}",
+                        row.chars().count(),
+                        width
+                    ),
+                ));
+                continue;
+            }
+            for (x, cell) in row.chars().enumerate() {
+                let position = Position { x, y };
+                let on_border = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+                let problem = match cell {
+                    '#' | 'X' => {
+                        walls.push(position);
+                        continue;
+                    }
+                    _ if on_border => {
+                        format!("column {} is on the border and must be a wall", x + 1)
+                    }
+                    '.' => continue,
+                    'S' => {
+                        starts.push(position);
+                        continue;
+                    }
+                    'F' => {
+                        food.push(position);
+                        continue;
+                    }
+                    'P' => format!("portals (`P`) aren't supported yet, in column {}", x + 1),
+                    _ => format!("unknown cell `{}` in column {}", cell, x + 1),
+                };
+                problems.push(parse_error(line, problem));
+            }
+        }
+
+        match starts[..] {
+            [_] => {}
+            [] => problems.push(map_error("the map has no start (`S`)".to_string())),
+            _ => problems.push(map_error(format!(
+                "the map has {} starts (`S`), but needs exactly one",
+                starts.len()
+            ))),
+        }
+        // Reachability only means something on a well-formed grid
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+
+        let start = starts[0];
+        let board = Board::with_walls(width, height, &walls);
+        let reachable = reachable_from(&board, start);
+        for stranded in food.iter().filter(|f| !reachable[f.y * width + f.x]) {
+            problems.push(parse_error(
+                stranded.y + 1,
+                format!(
+                    "the food in column {} can't be reached from the start",
+                    stranded.x + 1
+                ),
+            ));
+        }
+        // Random food could land in a sealed-off pocket, and the board could
+        // never be filled to win
+        let sealed = board.playable_cells() - reachable.iter().filter(|r| **r).count();
+        if sealed > 0 {
+            problems.push(map_error(format!(
+                "{} floor cells can't be reached from the start, so the board can never be filled",
+                sealed
+            )));
+        }
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+
+        Ok(Map { board, start, food })
+    }
+}
+
+// Which cells the snake could get to from `start`, indexed by y * width + x
+fn reachable_from(board: &Board, start: Position) -> Vec<bool> {
+    let mut reached = vec![false; board.width * board.height];
+    let mut queue = VecDeque::from([start]);
+    reached[start.y * board.width + start.x] = true;
+    while let Some(position) = queue.pop_front() {
+        for next in Direction::ALL.map(|direction| position.neighbor(direction)) {
+            let index = next.y * board.width + next.x;
+            if !reached[index] && !board.is_wall(next) {
+                reached[index] = true;
+                queue.push_back(next);
+            }
+        }
+    }
+    reached
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(rows: &[&str]) -> Result<Map, Vec<Error>> {
+        Map::parse(&rows.join("\n"), Path::new("level.txt"))
+    }
+
+    #[test]
+    fn parses_walls_start_and_food() {
+        let map = parse(&["######", "#S..F#", "#.X..#", "#....#", "######"]).unwrap();
+        assert_eq!((map.board.width, map.board.height), (6, 5));
+        assert_eq!(map.start, Position { x: 1, y: 1 });
+        assert_eq!(map.food, vec![Position { x: 4, y: 1 }]);
+        assert!(map.board.is_wall(Position { x: 2, y: 2 }));
+        assert_eq!(map.board.playable_cells(), 11);
+    }
+
+    #[test]
+    fn ignores_blank_lines_at_the_end() {
+        let map = parse(&["#####", "#S..#", "#...#", "#...#", "#####", "", ""]).unwrap();
+        assert_eq!(map.board.height, 5);
+    }
+
+    #[test]
+    fn rejects_maps_below_the_minimum_size() {
+        let problems = parse(&["####", "#S.#", "####"]).unwrap_err();
+        assert!(matches!(problems[..], [Error::Map { .. }]));
+    }
+
+    #[test]
+    fn reports_every_bad_row_with_its_line() {
+        let problems = parse(&["#####", "#S..#", "#..#", "#.?.#", ".####"]).unwrap_err();
+        let lines: Vec<usize> = problems
+            .iter()
+            .map(|problem| match problem {
+                Error::Parse { line, .. } => *line,
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(lines, vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn requires_exactly_one_start() {
+        let none = parse(&["#####", "#...#", "#...#", "#...#", "#####"]).unwrap_err();
+        assert!(matches!(none[..], [Error::Map { .. }]));
+        let two = parse(&["#####", "#S..#", "#...#", "#..S#", "#####"]).unwrap_err();
+        assert!(matches!(two[..], [Error::Map { .. }]));
+    }
+
+    #[test]
+    fn rejects_portals() {
+        let problems = parse(&["#####", "#S.P#", "#...#", "#...#", "#####"]).unwrap_err();
+        assert!(matches!(problems[..], [Error::Parse { line: 2, .. }]));
+    }
+
+    #[test]
+    fn rejects_floor_and_food_the_snake_cannot_reach() {
+        let problems = parse(&["######", "#S.#F#", "#..###", "#....#", "######"]).unwrap_err();
+        assert!(matches!(
+            problems[..],
+            [Error::Parse { line: 2, .. }, Error::Map { .. }]
+        ));
+    }
+
+    #[test]
+    fn load_reports_a_missing_file() {
+        let error = Map::load("no/such/level.txt").unwrap_err();
+        assert!(matches!(error, Error::Io { .. }));
+    }
+}
@@ -0,0 +1,87 @@
+// One bit per cell recording which cells the snake covers, so checking a cell
+// costs the same however long the snake is. Kept in step with the body as it
+// moves, which costs a bit set and a bit clear per step.
+//
+// A ghost snake can pass over itself, putting two segments on one cell. The
+// bit only says "at least one", so every segment beyond the first on a cell
+// is listed in `stacked`. That list is empty except while a ghost snake
+// overlaps itself, and its length is exactly how many overlaps there are.
+
+use crate::Position;
+
+// Free rows and columns added when the snake moves beyond the grid, so a
+// growing board isn't rebuilt on every step
+const GROWTH_MARGIN: usize = 8;
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Occupancy {
+    width: usize,
+    height: usize,
+    bits: Vec<u64>,
+    stacked: Vec<Position>,
+}
+
+impl Occupancy {
+    pub(crate) fn from_cells<'a>(cells: impl IntoIterator<Item = &'a Position>) -> Self {
+        let mut occupancy = Occupancy::default();
+        for cell in cells {
+            occupancy.insert(*cell);
+        }
+        occupancy
+    }
+
+    pub(crate) fn contains(&self, position: Position) -> bool {
+        position.x < self.width
+            && position.y < self.height
+            && self.bits[self.index(position) / 64] & self.mask(position) != 0
+    }
+
+    // Whether more than one segment is on `position`
+    pub(crate) fn is_stacked(&self, position: Position) -> bool {
+        self.stacked.contains(&position)
+    }
+
+    pub(crate) fn insert(&mut self, position: Position) {
+        if position.x >= self.width || position.y >= self.height {
+            self.grow_to_fit(position);
+        }
+        if self.contains(position) {
+            self.stacked.push(position);
+        } else {
+            let (word, mask) = (self.index(position) / 64, self.mask(position));
+            self.bits[word] |= mask;
+        }
+    }
+
+    pub(crate) fn remove(&mut self, position: Position) {
+        if let Some(i) = self.stacked.iter().position(|cell| *cell == position) {
+            self.stacked.swap_remove(i);
+        } else if self.contains(position) {
+            let (word, mask) = (self.index(position) / 64, self.mask(position));
+            self.bits[word] &= !mask;
+        }
+    }
+
+    fn index(&self, position: Position) -> usize {
+        position.y * self.width + position.x
+    }
+
+    fn mask(&self, position: Position) -> u64 {
+        1 << (self.index(position) % 64)
+    }
+
+    // Re-lay the bits out on a grid big enough for `position`
+    fn grow_to_fit(&mut self, position: Position) {
+        let cells: Vec<Position> = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| Position { x, y }))
+            .filter(|cell| self.contains(*cell))
+            .collect();
+        self.width = self.width.max(position.x + 1 + GROWTH_MARGIN);
+        self.height = self.height.max(position.y + 1 + GROWTH_MARGIN);
+        self.bits = vec![0; (self.width * self.height).div_ceil(64)];
+        for cell in cells {
+            let (word, mask) = (self.index(cell) / 64, self.mask(cell));
+            self.bits[word] |= mask;
+        }
+    }
+}
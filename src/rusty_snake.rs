@@ -0,0 +1,4319 @@
+use minifb::{Key, Window};
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use food_bag::FoodBag;
+#[cfg(feature = "occupancy-bitset")]
+use occupancy::Occupancy;
+
+mod autopilot;
+mod background;
+pub mod effects;
+mod error;
+mod food_bag;
+mod map;
+#[cfg(feature = "occupancy-bitset")]
+mod occupancy;
+pub mod storage;
+mod telemetry;
+mod text;
+mod theme;
+
+pub use autopilot::AutopilotStrategy;
+pub use background::Background;
+pub use error::Error;
+pub use map::Map;
+pub use telemetry::{NoTelemetry, Telemetry};
+pub use theme::Theme;
+
+pub const WINDOW_WIDTH: usize = 1280;
+pub const WINDOW_HEIGHT: usize = 720;
+pub const GRID_SIZE: usize = 20;
+pub const GRID_WIDTH: usize = WINDOW_WIDTH / GRID_SIZE;
+pub const GRID_HEIGHT: usize = WINDOW_HEIGHT / GRID_SIZE;
+pub const MIN_REFRESH_RATE: u64 = 16;
+pub const SLOW_REFRESH_RATE: u64 = 1000;
+pub const MIN_BOARD_SIZE: usize = 5;
+const MIN_CELL_SIZE: usize = 4;
+// Random guesses an early-game spawn makes before listing the free cells
+const EARLY_SPAWN_ATTEMPTS: usize = 64;
+const ADAPTIVE_GRID_FILL: f32 = 0.4;
+const ADAPTIVE_GRID_STEP: usize = 4;
+const LEGEND_SCALE: usize = 2;
+const LEGEND_PADDING: usize = 12;
+const VICTORY_DIM: f32 = 0.6;
+const CELEBRATION_BLINK_MS: u128 = 250;
+const PAUSE_DIM: f32 = 0.6;
+const ATTRACT_DIM: f32 = 0.4;
+// Menu entries that aren't selected are drawn in gray
+const PAUSE_INACTIVE: u32 = 0x808080;
+// Speeds offered on the options screen in milliseconds per step, slowest first
+const SPEED_STEPS: [u64; 6] = [250, 200, 150, 100, 70, 50];
+// Cell sizes in pixels the camera on large boards zooms between, farthest
+// out first. Starts at GRID_SIZE.
+const ZOOM_STEPS: [usize; 7] = [MIN_CELL_SIZE, 6, 9, 13, GRID_SIZE, 28, 40];
+const DEFAULT_ZOOM: usize = 4;
+// How far the second color of a patterned border fades toward the background
+const BORDER_PATTERN_FADE: f32 = 0.5;
+// How strongly the scoring zone tints the background toward the border color
+const ZONE_TINT: f32 = 0.15;
+// How far the snake fades toward the background while it is a ghost, and
+// how far ghost food is lightened from the food color to tell it apart
+const GHOST_FADE: f32 = 0.5;
+// Color and strength of the tint over cells next to the head that would
+// kill the snake, light enough that the wall or body underneath still shows
+const DANGER_COLOR: u32 = 0xFF0000;
+const DANGER_TINT: f32 = 0.4;
+// How far pellets that aren't next in line fade toward the background when
+// food must be eaten in order
+const ORDER_FADE: f32 = 0.5;
+// Sparks thrown out when food is eaten: how many per pellet, how many ticks
+// they last, how far they fly per tick in cells, and the most alive at once
+const PARTICLES_PER_BURST: usize = 8;
+const PARTICLE_TICKS: u32 = 4;
+const PARTICLE_SPEED: f32 = 0.5;
+const MAX_PARTICLES: usize = 48;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub x: usize,
+    pub y: usize,
+}
+
+impl Position {
+    // The adjacent cell in `direction`, without any bounds clamping. Only
+    // valid for interior cells, which always have a neighbor on every side.
+    fn neighbor(self, direction: Direction) -> Position {
+        match direction {
+            Direction::Up => Position {
+                x: self.x,
+                y: self.y - 1,
+            },
+            Direction::Down => Position {
+                x: self.x,
+                y: self.y + 1,
+            },
+            Direction::Left => Position {
+                x: self.x - 1,
+                y: self.y,
+            },
+            Direction::Right => Position {
+                x: self.x + 1,
+                y: self.y,
+            },
+        }
+    }
+
+    /// Steps between the two cells moving only along rows and columns.
+    pub fn manhattan_distance(self, other: Position) -> usize {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
+    }
+
+    /// The direction that steps from this cell onto `other`, or `None` if
+    /// `other` isn't directly above, below, left or right of it.
+    pub fn direction_to(self, other: Position) -> Option<Direction> {
+        match (
+            other.x as isize - self.x as isize,
+            other.y as isize - self.y as isize,
+        ) {
+            (0, -1) => Some(Direction::Up),
+            (0, 1) => Some(Direction::Down),
+            (-1, 0) => Some(Direction::Left),
+            (1, 0) => Some(Direction::Right),
+            _ => None,
+        }
+    }
+}
+
+/// Shown as `(x, y)`.
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    fn opposite(self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    fn mirrored(self, horizontal: bool, vertical: bool) -> Self {
+        match self {
+            Direction::Left if horizontal => Direction::Right,
+            Direction::Right if horizontal => Direction::Left,
+            Direction::Up if vertical => Direction::Down,
+            Direction::Down if vertical => Direction::Up,
+            direction => direction,
+        }
+    }
+}
+
+/// Shown as `Up`, `Down`, `Left` or `Right`.
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Direction::Up => "Up",
+            Direction::Down => "Down",
+            Direction::Left => "Left",
+            Direction::Right => "Right",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The key that steers the snake in each direction, plus the keys for the
+/// actions players most often want somewhere else on their keyboard.
+#[derive(Clone, Copy, Debug)]
+pub struct KeyBindings {
+    pub up: Key,
+    pub down: Key,
+    pub left: Key,
+    pub right: Key,
+    /// Hands control to the autopilot and back.
+    pub toggle_autopilot: Key,
+    /// Starts a new game once this one is over.
+    pub restart: Key,
+    /// Opens the pause menu, or closes it again.
+    pub pause: Key,
+    /// Closes the game. `Q` also quits unless it is bound to something else.
+    pub quit: Key,
+    /// Leaves the game for the title screen, with the demo playing behind it.
+    pub menu: Key,
+}
+
+// Keys with a fixed meaning, which no binding may take over: Enter picks menu
+// entries, and the rest are the legend, danger, theme, mark and zoom keys
+const RESERVED_KEYS: [Key; 11] = [
+    Key::Enter,
+    Key::Backspace,
+    Key::L,
+    Key::H,
+    Key::T,
+    Key::C,
+    Key::M,
+    Key::Equal,
+    Key::Minus,
+    Key::NumPadPlus,
+    Key::NumPadMinus,
+];
+
+impl KeyBindings {
+    // Every binding with a name for error messages
+    fn named(&self) -> [(&'static str, Key); 9] {
+        [
+            ("up", self.up),
+            ("down", self.down),
+            ("left", self.left),
+            ("right", self.right),
+            ("autopilot", self.toggle_autopilot),
+            ("restart", self.restart),
+            ("pause", self.pause),
+            ("quit", self.quit),
+            ("menu", self.menu),
+        ]
+    }
+
+    fn binds(&self, key: Key) -> bool {
+        self.named().iter().any(|(_, bound)| *bound == key)
+    }
+
+    fn quit_pressed(&self, window: &Window) -> bool {
+        window.is_key_pressed(self.quit, minifb::KeyRepeat::No)
+            || (!self.binds(Key::Q) && window.is_key_pressed(Key::Q, minifb::KeyRepeat::No))
+    }
+
+    fn pressed_directions(&self, window: &Window) -> Vec<Direction> {
+        [
+            (self.up, Direction::Up),
+            (self.down, Direction::Down),
+            (self.left, Direction::Left),
+            (self.right, Direction::Right),
+        ]
+        .into_iter()
+        .filter(|(key, _)| window.is_key_pressed(*key, minifb::KeyRepeat::No))
+        .map(|(_, direction)| direction)
+        .collect()
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            up: Key::Up,
+            down: Key::Down,
+            left: Key::Left,
+            right: Key::Right,
+            toggle_autopilot: Key::A,
+            restart: Key::R,
+            pause: Key::P,
+            quit: Key::Escape,
+            menu: Key::Home,
+        }
+    }
+}
+
+/// The playfield dimensions in cells, including the one-cell wall border,
+/// and any walls inside it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Board {
+    pub width: usize,
+    pub height: usize,
+    // Which cells inside the border are walls too, indexed by
+    // `y * width + x`, or `None` for an open board
+    inner_walls: Option<Arc<[bool]>>,
+    inner_wall_count: usize,
+}
+
+impl Board {
+    pub fn new(width: usize, height: usize) -> Self {
+        Board {
+            width,
+            height,
+            inner_walls: None,
+            inner_wall_count: 0,
+        }
+    }
+
+    /// A board with extra walls at `walls` besides the border. Positions
+    /// outside the board or on the border are ignored.
+    pub fn with_walls(width: usize, height: usize, walls: &[Position]) -> Self {
+        let mut board = Board::new(width, height);
+        let mut grid = vec![false; width * height];
+        for wall in walls {
+            if wall.x < width && wall.y < height && !board.is_wall(*wall) {
+                grid[wall.y * width + wall.x] = true;
+            }
+        }
+        board.inner_wall_count = grid.iter().filter(|wall| **wall).count();
+        if board.inner_wall_count > 0 {
+            board.inner_walls = Some(grid.into());
+        }
+        board
+    }
+
+    /// Whether the board has walls inside its border.
+    pub fn has_inner_walls(&self) -> bool {
+        self.inner_wall_count > 0
+    }
+
+    fn center(&self) -> Position {
+        Position {
+            x: self.width / 2,
+            y: self.height / 2,
+        }
+    }
+
+    fn is_wall(&self, position: Position) -> bool {
+        position.x == 0
+            || position.x >= self.width - 1
+            || position.y == 0
+            || position.y >= self.height - 1
+            || self
+                .inner_walls
+                .as_ref()
+                .is_some_and(|walls| walls[position.y * self.width + position.x])
+    }
+
+    // The nearest cell inside the walls
+    fn clamp_to_interior(&self, position: Position) -> Position {
+        Position {
+            x: position.x.clamp(1, self.width - 2),
+            y: position.y.clamp(1, self.height - 2),
+        }
+    }
+
+    /// How many cells the snake can occupy: everything inside the border
+    /// that isn't a wall itself.
+    pub fn playable_cells(&self) -> usize {
+        (self.width - 2) * (self.height - 2) - self.inner_wall_count
+    }
+
+    // Largest cell size in pixels that fits the whole board in the window
+    fn cell_size(&self) -> usize {
+        (WINDOW_WIDTH / self.width).min(WINDOW_HEIGHT / self.height)
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::new(GRID_WIDTH, GRID_HEIGHT)
+    }
+}
+
+// The window of board cells currently drawn on screen. When the cells don't
+// fill the window exactly, the drawn area is centered and the leftover
+// margins are left in the background color.
+struct Viewport {
+    origin: Position,
+    cols: usize,
+    rows: usize,
+    cell_size: usize,
+    // Pixel position of the top-left corner of the drawn area
+    offset_x: usize,
+    offset_y: usize,
+}
+
+impl Viewport {
+    // Show the whole board when it fits with cells of at least MIN_CELL_SIZE
+    // pixels. Larger boards are drawn with cells of ZOOM_STEPS[zoom] pixels
+    // through a camera centered on `focus` (the snake's head), clamped so it
+    // never scrolls past the walls.
+    fn new(board: &Board, focus: Position, zoom: usize) -> Self {
+        let fit = board.cell_size();
+        if fit >= MIN_CELL_SIZE {
+            return Viewport::centered(Position { x: 0, y: 0 }, board.width, board.height, fit);
+        }
+
+        let cell_size = ZOOM_STEPS[zoom];
+        let cols = (WINDOW_WIDTH / cell_size).min(board.width);
+        let rows = (WINDOW_HEIGHT / cell_size).min(board.height);
+        let origin = Position {
+            x: focus.x.saturating_sub(cols / 2).min(board.width - cols),
+            y: focus.y.saturating_sub(rows / 2).min(board.height - rows),
+        };
+        Viewport::centered(origin, cols, rows, cell_size)
+    }
+
+    fn centered(origin: Position, cols: usize, rows: usize, cell_size: usize) -> Self {
+        Viewport {
+            origin,
+            cols,
+            rows,
+            cell_size,
+            offset_x: WINDOW_WIDTH.saturating_sub(cols * cell_size) / 2,
+            offset_y: WINDOW_HEIGHT.saturating_sub(rows * cell_size) / 2,
+        }
+    }
+
+    fn cells(&self) -> impl Iterator<Item = Position> + '_ {
+        (self.origin.y..self.origin.y + self.rows).flat_map(move |y| {
+            (self.origin.x..self.origin.x + self.cols).map(move |x| Position { x, y })
+        })
+    }
+
+    // Top-left pixel of a board cell, or None if the cell is off screen
+    fn screen_position(&self, position: Position) -> Option<(usize, usize)> {
+        if position.x < self.origin.x
+            || position.x >= self.origin.x + self.cols
+            || position.y < self.origin.y
+            || position.y >= self.origin.y + self.rows
+        {
+            return None;
+        }
+        Some((
+            self.offset_x + (position.x - self.origin.x) * self.cell_size,
+            self.offset_y + (position.y - self.origin.y) * self.cell_size,
+        ))
+    }
+
+    // Fill the screen square for a board cell, skipping off-screen cells
+    fn fill_cell(&self, buffer: &mut [u32], position: Position, color: u32) {
+        self.fill_cell_with(buffer, position, |_, _| color);
+    }
+
+    // Mixes `amount` of `color` into every pixel already drawn in a cell
+    fn tint_cell(&self, buffer: &mut [u32], position: Position, color: u32, amount: f32) {
+        let Some((start_x, start_y)) = self.screen_position(position) else {
+            return;
+        };
+        for y in start_y..(start_y + self.cell_size).min(WINDOW_HEIGHT) {
+            for x in start_x..(start_x + self.cell_size).min(WINDOW_WIDTH) {
+                let pixel = &mut buffer[y * WINDOW_WIDTH + x];
+                *pixel = effects::blend(*pixel, color, amount);
+            }
+        }
+    }
+
+    // Fill the `fraction` of a cell on its `side`, leaving the rest as drawn
+    fn fill_cell_part(
+        &self,
+        buffer: &mut [u32],
+        position: Position,
+        side: Direction,
+        fraction: f32,
+        color: u32,
+    ) {
+        let Some((start_x, start_y)) = self.screen_position(position) else {
+            return;
+        };
+        let covered = (self.cell_size as f32 * fraction).round() as usize;
+        let far = self.cell_size - covered;
+        for y in 0..self.cell_size {
+            for x in 0..self.cell_size {
+                let inside = match side {
+                    Direction::Up => y < covered,
+                    Direction::Down => y >= far,
+                    Direction::Left => x < covered,
+                    Direction::Right => x >= far,
+                };
+                if inside && start_y + y < WINDOW_HEIGHT && start_x + x < WINDOW_WIDTH {
+                    buffer[(start_y + y) * WINDOW_WIDTH + start_x + x] = color;
+                }
+            }
+        }
+    }
+
+    // Like `fill_cell`, but each pixel's color comes from `color_at`, given
+    // the pixel's offset from the cell's top-left corner
+    fn fill_cell_with(
+        &self,
+        buffer: &mut [u32],
+        position: Position,
+        color_at: impl Fn(usize, usize) -> u32,
+    ) {
+        let Some((start_x, start_y)) = self.screen_position(position) else {
+            return;
+        };
+        for y in start_y..start_y + self.cell_size {
+            for x in start_x..start_x + self.cell_size {
+                if y < WINDOW_HEIGHT && x < WINDOW_WIDTH {
+                    buffer[y * WINDOW_WIDTH + x] = color_at(x - start_x, y - start_y);
+                }
+            }
+        }
+    }
+}
+
+/// Something notable that happened during a tick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameEvent {
+    /// The snake ate the food at this position.
+    Ate(Position),
+    /// The snake hit a wall or itself.
+    Died,
+    /// The board filled up or the win condition was reached.
+    Won,
+    /// The head crossed into the scoring zone, earning its bonus.
+    EnteredZone,
+}
+
+/// The entries of the pause menu, top to bottom.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PauseChoice {
+    /// Close the menu and carry on.
+    Resume,
+    /// Start a new game.
+    Restart,
+    /// Open the options screen.
+    Options,
+    /// Close the game.
+    Quit,
+}
+
+impl PauseChoice {
+    pub const ALL: [PauseChoice; 4] = [
+        PauseChoice::Resume,
+        PauseChoice::Restart,
+        PauseChoice::Options,
+        PauseChoice::Quit,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            PauseChoice::Resume => "RESUME",
+            PauseChoice::Restart => "RESTART",
+            PauseChoice::Options => "OPTIONS",
+            PauseChoice::Quit => "QUIT",
+        }
+    }
+}
+
+/// Settings the options screen changes. Changes take effect from the next
+/// new game.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GameOptions {
+    /// Milliseconds per step.
+    pub refresh_rate: u64,
+    pub theme: Theme,
+    /// See `GameConfig::wall_grace`.
+    pub wall_grace: bool,
+}
+
+// Rows of the options screen, top to bottom
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum OptionsRow {
+    Speed,
+    Theme,
+    Walls,
+    Back,
+}
+
+impl OptionsRow {
+    const ALL: [OptionsRow; 4] = [
+        OptionsRow::Speed,
+        OptionsRow::Theme,
+        OptionsRow::Walls,
+        OptionsRow::Back,
+    ];
+}
+
+// The entry `offset` places down a menu from `current`, wrapping at either end
+fn menu_moved<T: Copy + PartialEq>(entries: &[T], current: T, offset: isize) -> T {
+    let len = entries.len() as isize;
+    let index = entries.iter().position(|e| *e == current).unwrap_or(0) as isize;
+    entries[(index + offset).rem_euclid(len) as usize]
+}
+
+/// What a move would run the snake into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CollisionKind {
+    /// The border or an obstacle.
+    Wall,
+    /// One of the snake's own segments.
+    Body,
+}
+
+/// A `GameEvent` stamped with the tick it happened on, counted the same way
+/// as `Game::ticks`, so the first step's events carry tick 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimedEvent {
+    pub tick: u64,
+    pub event: GameEvent,
+}
+
+/// A goal that ends the game as a win before the board is full. Filling the
+/// board is always a win, whether or not one of these is set.
+#[derive(Clone, Copy, Debug)]
+pub enum WinCondition {
+    TargetScore(u32),
+    TargetLength(usize),
+}
+
+/// What happens when the player presses the direction opposite the one the
+/// snake is moving in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+pub enum ReverseRule {
+    /// Drop the press, so the snake can never turn back on itself.
+    #[default]
+    Ignore,
+    /// Turn around, which is a collision with the neck for snakes of three
+    /// or more cells (or two while growing).
+    Collide,
+}
+
+/// Which seed a game uses when restarted after it ends.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum RestartSeed {
+    /// Replay the same seed, so food appears in the same places as before.
+    Same,
+    /// Draw a fresh random seed for a different board every time.
+    New,
+}
+
+/// Where on the board new food is allowed to appear.
+#[derive(Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+pub enum SpawnStrategy {
+    /// Every free cell is equally likely.
+    #[default]
+    Uniform,
+    /// Cells further from the walls are more likely, so food rarely ends
+    /// up pinned against the border.
+    CenterBiased,
+}
+
+/// A square bonus area that jumps to a new random spot every `shift_ticks`
+/// ticks. Crossing into it with the head scores `bonus` points; staying in
+/// it, or having it land on the head, scores nothing.
+#[derive(Clone, Copy, Debug)]
+pub struct ScoringZone {
+    /// Width and height of the zone in cells.
+    pub size: usize,
+    pub shift_ticks: u64,
+    pub bonus: u32,
+}
+
+/// A current that pushes the snake along: every `every_ticks` ticks, a snake
+/// that hasn't been steered since the last push turns toward `direction`.
+/// The current never turns the snake straight around.
+#[derive(Clone, Copy, Debug)]
+pub struct Drift {
+    pub direction: Direction,
+    pub every_ticks: u64,
+}
+
+// A spark from a burst, positioned in cells with fractions so it can sit
+// anywhere inside one
+#[derive(Clone, Copy, Debug)]
+struct Particle {
+    x: f32,
+    y: f32,
+    dx: f32,
+    dy: f32,
+    ticks_left: u32,
+    color: u32,
+}
+
+impl Particle {
+    // A small square, fading out as its ticks run down
+    fn render(&self, buffer: &mut [u32], viewport: &Viewport) {
+        if self.x < 0.0 || self.y < 0.0 {
+            return;
+        }
+        let cell = Position {
+            x: self.x as usize,
+            y: self.y as usize,
+        };
+        let Some((cell_x, cell_y)) = viewport.screen_position(cell) else {
+            return;
+        };
+        let size = (viewport.cell_size / 4).max(1);
+        let left = cell_x + (self.x.fract() * viewport.cell_size as f32) as usize;
+        let top = cell_y + (self.y.fract() * viewport.cell_size as f32) as usize;
+        let strength = self.ticks_left as f32 / PARTICLE_TICKS as f32;
+        for y in top..(top + size).min(WINDOW_HEIGHT) {
+            for x in left..(left + size).min(WINDOW_WIDTH) {
+                let pixel = &mut buffer[y * WINDOW_WIDTH + x];
+                *pixel = effects::blend(*pixel, self.color, strength);
+            }
+        }
+    }
+}
+
+// Where the scoring zone currently is
+#[derive(Clone, Copy, Debug)]
+struct Zone {
+    origin: Position,
+    size: usize,
+}
+
+impl Zone {
+    // A zone of `size` cells at a random spot fully inside the walls
+    fn random(board: &Board, size: usize, rng: &mut impl Rng) -> Self {
+        Zone {
+            origin: Position {
+                x: rng.random_range(1..=board.width - 1 - size),
+                y: rng.random_range(1..=board.height - 1 - size),
+            },
+            size,
+        }
+    }
+
+    fn contains(&self, position: Position) -> bool {
+        (self.origin.x..self.origin.x + self.size).contains(&position.x)
+            && (self.origin.y..self.origin.y + self.size).contains(&position.y)
+    }
+}
+
+/// How the wall around the board is drawn. Patterned styles alternate the
+/// border color with a faded copy of it rather than leaving gaps, so every
+/// wall cell still stands apart from the background.
+#[derive(Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+pub enum BorderStyle {
+    /// Every wall cell in the border color.
+    #[default]
+    Solid,
+    /// Wall cells alternate between the border color and the faded copy.
+    Dashed,
+    /// Each wall cell is split into a two-by-two checkerboard of the border
+    /// color and the faded copy.
+    Checkered,
+}
+
+/// How many points a pellet is worth. The value starts at `base` when the
+/// pellet spawns and drops by `decay` every tick it goes uneaten, but never
+/// below `floor`.
+#[derive(Clone, Copy, Debug)]
+pub struct FoodValue {
+    pub base: u32,
+    pub decay: u32,
+    pub floor: u32,
+}
+
+impl Default for FoodValue {
+    fn default() -> Self {
+        FoodValue {
+            base: 10,
+            decay: 0,
+            floor: 0,
+        }
+    }
+}
+
+impl FoodValue {
+    /// Points for a pellet that has sat uneaten for `age` ticks.
+    pub fn points(&self, age: u64) -> u32 {
+        let lost = u64::from(self.decay).saturating_mul(age);
+        let points = u64::from(self.base).saturating_sub(lost);
+        (points as u32).max(self.floor)
+    }
+}
+
+/// Decides how many points eating food scores, in place of `FoodValue`.
+///
+/// The function is called as the snake eats, with the game as it stands at
+/// that moment: the head is on the eaten food's cell, that food is already
+/// off the board, and neither the score nor the snake's length has changed
+/// yet. It gets the game by shared reference, so it can only read it.
+#[derive(Clone)]
+pub struct FoodScorer(Arc<ScoreFn>);
+
+type ScoreFn = dyn Fn(&Game, FoodKind) -> u32 + Send + Sync;
+
+impl FoodScorer {
+    pub fn new(score: impl Fn(&Game, FoodKind) -> u32 + Send + Sync + 'static) -> Self {
+        FoodScorer(Arc::new(score))
+    }
+}
+
+impl fmt::Debug for FoodScorer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("FoodScorer(..)")
+    }
+}
+
+/// Options that shape a game, fixed for its lifetime.
+#[derive(Clone, Debug)]
+pub struct GameConfig {
+    pub refresh_rate: u64,
+    /// Experimental: milliseconds per step while the snake moves up or
+    /// down, so it can cross tall cells at the same on-screen speed as wide
+    /// ones. `refresh_rate` then only applies to left and right moves. When
+    /// unset, every move uses `refresh_rate`.
+    pub vertical_refresh_rate: Option<u64>,
+    /// Boards too large for the window are drawn through a camera that
+    /// follows the snake's head.
+    pub board: Board,
+    /// Experimental: grow the board outward as the snake fills it.
+    pub adaptive_grid: bool,
+    pub key_bindings: KeyBindings,
+    /// Swap the Left and Right controls after key bindings are applied.
+    pub mirror_horizontal: bool,
+    /// Swap the Up and Down controls after key bindings are applied.
+    pub mirror_vertical: bool,
+    pub win_condition: Option<WinCondition>,
+    /// Let the computer steer instead of the keyboard. The player can take
+    /// over, or hand control back, with `KeyBindings::toggle_autopilot`.
+    pub autopilot: Option<AutopilotStrategy>,
+    /// Open on an attract demo: the autopilot plays behind the title,
+    /// starting over whenever its game ends, until any key starts a real
+    /// game.
+    pub attract: bool,
+    /// Start a new game this long after the last one ends, as if R had been
+    /// pressed, for displays left running unattended. R still restarts
+    /// straight away.
+    pub auto_restart: Option<Duration>,
+    /// Seed for food placement. A random seed is chosen when unset.
+    ///
+    /// Seeded games draw from ChaCha8, whose output is fixed by its
+    /// specification, so the same seed and inputs play out identically on
+    /// every platform and `rand` version. The random seeds picked when this
+    /// is unset (and for `RestartSeed::New`) carry no such guarantee.
+    pub seed: Option<u64>,
+    pub restart_seed: RestartSeed,
+    pub theme: Theme,
+    /// Drawn beneath the board instead of the theme's background color.
+    pub background: Option<Background>,
+    pub border_style: BorderStyle,
+    /// Replace `theme` with a palette derived from the seed.
+    pub random_colors: bool,
+    /// Development aid: walls stop the snake instead of killing it and it
+    /// can pass through itself, so it can grow without limit.
+    pub invincible: bool,
+    /// No food spawns and the snake never grows, for free driving around the
+    /// board.
+    pub zen: bool,
+    /// Score the game starts (and restarts) with.
+    pub start_score: u32,
+    /// Points awarded for every tick the snake moves and survives, on top of
+    /// what it eats. Ignored in zen mode.
+    pub survival_points: u32,
+    pub food_value: FoodValue,
+    /// Scores eaten food with custom rules instead of `food_value`, which
+    /// still sets the values drawn on decaying food. `food_value` is used
+    /// when unset.
+    pub food_scorer: Option<FoodScorer>,
+    /// How many of each food kind go into the shuffle-bag new food is drawn
+    /// from. Each full pass through the bag holds exactly these counts, in a
+    /// seeded random order.
+    pub food_mix: Vec<(FoodKind, usize)>,
+    /// Most pieces of food on the board at once. The board starts full.
+    pub max_food: usize,
+    /// While there is less than `max_food` on the board, one more piece
+    /// appears every this many ticks. At zero, eaten food is replaced
+    /// straight away.
+    pub food_regen_ticks: u64,
+    /// Puzzle mode: the pellets on the board are numbered in the order they
+    /// appeared and must be eaten oldest first. Eating any other pellet ends
+    /// the run. Needs `max_food` of at least 2.
+    pub ordered_food: bool,
+    /// How many ticks after eating ghost food the snake can pass through
+    /// itself.
+    pub ghost_ticks: u64,
+    pub spawn_strategy: SpawnStrategy,
+    /// Keep new food off the cells next to the tail, which the snake could
+    /// otherwise grab trivially as its tail moves away. Ignored when those
+    /// are the only free cells left.
+    pub food_avoids_tail: bool,
+    /// Best score to beat. Leave unset to skip high score tracking, e.g.
+    /// for autopilot runs.
+    pub high_score: Option<u32>,
+    /// Cell the snake starts on. Defaults to the center of the board.
+    pub start: Option<Position>,
+    /// Cells that hold normal food at the start of every game, before the
+    /// rest of `max_food` is placed at random. Ignored in zen mode.
+    pub initial_food: Vec<Position>,
+    /// Cells new food is placed on, in order, before falling back to random
+    /// placement once the list runs out. An entry whose cell is taken when
+    /// its turn comes is skipped. Starts over on restart.
+    pub food_sequence: Vec<Position>,
+    /// Extra cells the snake grows by over its first moves, without eating,
+    /// so it starts as a single cell and stretches out to
+    /// `1 + warmup_growth` cells. Ignored in zen mode.
+    pub warmup_growth: usize,
+    /// The snake every game starts with, in place of the one-cell snake on
+    /// `start`. `start` and `warmup_growth` are ignored when this is set,
+    /// and in zen mode the snake's pending growth is dropped.
+    pub snake: Option<Snake>,
+    pub reverse_rule: ReverseRule,
+    /// A bonus area that moves around the board. Off when unset.
+    pub scoring_zone: Option<ScoringZone>,
+    /// Experimental: a current that turns the snake when left alone. Off
+    /// when unset.
+    pub drift: Option<Drift>,
+    /// Forgiving walls: a move into a wall is held back for one tick, giving
+    /// the player one more tick to turn away. The snake dies if it is still
+    /// heading into the wall on the next tick.
+    pub wall_grace: bool,
+    /// Start with the danger hints on: cells next to the head that the
+    /// snake would die moving into are tinted red. `H` toggles them.
+    pub danger_hints: bool,
+    /// Cosmetic: after a step on which the snake grew, draw its tail
+    /// stretching back into its cell over the next step instead of sitting
+    /// there whole. Collisions always use whole cells.
+    pub animate_growth: bool,
+    /// Cosmetic: throw a burst of fading sparks out of each pellet as it is
+    /// eaten.
+    pub particles: bool,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            refresh_rate: 150,
+            vertical_refresh_rate: None,
+            board: Board::default(),
+            adaptive_grid: false,
+            key_bindings: KeyBindings::default(),
+            mirror_horizontal: false,
+            mirror_vertical: false,
+            win_condition: None,
+            autopilot: None,
+            attract: false,
+            auto_restart: None,
+            seed: None,
+            restart_seed: RestartSeed::New,
+            theme: Theme::default(),
+            background: None,
+            border_style: BorderStyle::default(),
+            random_colors: false,
+            invincible: false,
+            zen: false,
+            start_score: 0,
+            survival_points: 0,
+            food_value: FoodValue::default(),
+            food_scorer: None,
+            food_mix: vec![(FoodKind::Normal, 1)],
+            max_food: 1,
+            ordered_food: false,
+            food_avoids_tail: false,
+            food_regen_ticks: 0,
+            ghost_ticks: 20,
+            spawn_strategy: SpawnStrategy::default(),
+            high_score: None,
+            start: None,
+            initial_food: Vec::new(),
+            food_sequence: Vec::new(),
+            warmup_growth: 0,
+            snake: None,
+            reverse_rule: ReverseRule::default(),
+            scoring_zone: None,
+            drift: None,
+            wall_grace: false,
+            danger_hints: false,
+            animate_growth: false,
+            particles: false,
+        }
+    }
+}
+
+impl GameConfig {
+    /// Sets `food_sequence`, for scripting exactly where food appears.
+    pub fn with_food_sequence(mut self, sequence: Vec<Position>) -> Self {
+        self.food_sequence = sequence;
+        self
+    }
+
+    /// Sets `snake`, for starting from a particular position, length or
+    /// heading.
+    pub fn with_snake(mut self, snake: Snake) -> Self {
+        self.snake = Some(snake);
+        self
+    }
+
+    /// Checks the options that depend on each other, such as the start
+    /// position fitting on the board.
+    pub fn validate(&self) -> Result<(), Error> {
+        // Everything below assumes there is floor inside the walls
+        if self.board.width < MIN_BOARD_SIZE || self.board.height < MIN_BOARD_SIZE {
+            return Err(Error::Validation(format!(
+                "the board is {}x{}, but needs to be at least {}x{}",
+                self.board.width, self.board.height, MIN_BOARD_SIZE, MIN_BOARD_SIZE
+            )));
+        }
+        if let Some(zone) = self.scoring_zone {
+            let fits = self.board.width.min(self.board.height).saturating_sub(2);
+            if zone.size == 0 || zone.size > fits {
+                return Err(Error::Validation(format!(
+                    "scoring zone size must be 1 to {} to fit inside the {}x{} board",
+                    fits, self.board.width, self.board.height
+                )));
+            }
+            if zone.shift_ticks == 0 {
+                return Err(Error::Validation(
+                    "scoring zone must stay put for at least one tick".to_string(),
+                ));
+            }
+        }
+        if self.drift.is_some_and(|drift| drift.every_ticks == 0) {
+            return Err(Error::Validation(
+                "drift must wait at least one tick between pushes".to_string(),
+            ));
+        }
+        if self.max_food == 0 || self.max_food >= self.board.playable_cells() {
+            return Err(Error::Validation(format!(
+                "max food must be 1 to {} to leave the snake room on the {}x{} board",
+                self.board.playable_cells().saturating_sub(1),
+                self.board.width,
+                self.board.height
+            )));
+        }
+        if self.ordered_food && self.max_food < 2 {
+            return Err(Error::Validation(
+                "eating food in order needs a max food of at least 2".to_string(),
+            ));
+        }
+        if self.food_mix.iter().all(|(_, count)| *count == 0) {
+            return Err(Error::Validation(
+                "the food mix needs at least one food kind with a nonzero count".to_string(),
+            ));
+        }
+        if let Some(start) = self.start {
+            if start.x >= self.board.width || start.y >= self.board.height {
+                return Err(Error::Validation(format!(
+                    "start position {} is outside the {}x{} board",
+                    start, self.board.width, self.board.height
+                )));
+            }
+            if self.board.is_wall(start) {
+                return Err(Error::Validation(format!(
+                    "start position {} is on the wall; x must be 1 to {} and y 1 to {}",
+                    start,
+                    self.board.width - 2,
+                    self.board.height - 2
+                )));
+            }
+        }
+        if let Some(snake) = &self.snake {
+            if self.start.is_some() {
+                return Err(Error::Validation(
+                    "a starting snake can't be combined with a start position".to_string(),
+                ));
+            }
+            if let Some(segment) = snake.body.iter().find(|cell| self.board.is_wall(**cell)) {
+                return Err(Error::Validation(format!(
+                    "snake segment {} must be inside the walls",
+                    segment
+                )));
+            }
+        }
+        // Cells the snake covers when each game starts
+        let start_cells: Vec<Position> = match &self.snake {
+            Some(snake) => snake.body.iter().copied().collect(),
+            None => vec![self.start.unwrap_or(self.board.center())],
+        };
+        if self.board.has_inner_walls() && (self.adaptive_grid || self.invincible) {
+            return Err(Error::Validation(
+                "walls inside the board can't be combined with the adaptive grid or invincibility"
+                    .to_string(),
+            ));
+        }
+        if self.initial_food.len() > self.max_food {
+            return Err(Error::Validation(format!(
+                "{} pieces of initial food don't fit under a max food of {}",
+                self.initial_food.len(),
+                self.max_food
+            )));
+        }
+        for (i, food) in self.initial_food.iter().enumerate() {
+            if food.x >= self.board.width
+                || food.y >= self.board.height
+                || self.board.is_wall(*food)
+                || start_cells.contains(food)
+                || self.initial_food[..i].contains(food)
+            {
+                return Err(Error::Validation(format!(
+                    "initial food at {} must be on a free cell of its own",
+                    food
+                )));
+            }
+        }
+        if !self.food_sequence.is_empty() && self.adaptive_grid {
+            return Err(Error::Validation(
+                "a food sequence can't be combined with the adaptive grid, which moves every cell"
+                    .to_string(),
+            ));
+        }
+        if let Some(food) = self
+            .food_sequence
+            .iter()
+            .find(|food| self.board.is_wall(**food) || start_cells.contains(food))
+        {
+            return Err(Error::Validation(format!(
+                "food sequence entry {} must be inside the walls and off the starting snake",
+                food
+            )));
+        }
+        let bindings = self.key_bindings.named();
+        if let Some((name, key)) = bindings.iter().find(|(_, key)| RESERVED_KEYS.contains(key)) {
+            return Err(Error::Validation(format!(
+                "{:?} can't be bound to {}, since it already has a fixed use",
+                key, name
+            )));
+        }
+        for (i, (name, key)) in bindings.iter().enumerate() {
+            if let Some((other, _)) = bindings[i + 1..].iter().find(|(_, k)| k == key) {
+                return Err(Error::Validation(format!(
+                    "{:?} is bound to both {} and {}",
+                    key, name, other
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Snake {
+    body: VecDeque<Position>,
+    direction: Direction,
+    queued_direction: Option<Direction>,
+    // Moves left on which the tail stays put so the snake gets longer
+    pending_growth: usize,
+    // The cells `body` covers, for lookups that don't scan the body
+    #[cfg(feature = "occupancy-bitset")]
+    occupied: Occupancy,
+}
+
+impl Snake {
+    // A one-cell snake heading Right that grows over its first
+    // `pending_growth` moves
+    fn new(start: Position, pending_growth: usize) -> Self {
+        Snake::with(vec![start], Direction::Right, pending_growth)
+            .expect("a one-cell body is never empty")
+    }
+
+    /// A snake with the given body, listed from head to tail, heading Right
+    /// and not growing. Returns `None` if `body` is empty.
+    pub fn init(body: Vec<Position>) -> Option<Self> {
+        Snake::with(body, Direction::Right, 0)
+    }
+
+    /// A snake with the given body, listed from head to tail, moving in
+    /// `direction` and due to grow by `pending_growth` cells over its next
+    /// moves. Returns `None` if `body` is empty. Start a game from it with
+    /// `GameConfig::with_snake`.
+    pub fn with(body: Vec<Position>, direction: Direction, pending_growth: usize) -> Option<Self> {
+        if body.is_empty() {
+            return None;
+        }
+        Some(Snake {
+            #[cfg(feature = "occupancy-bitset")]
+            occupied: Occupancy::from_cells(&body),
+            body: VecDeque::from(body),
+            direction,
+            queued_direction: None,
+            pending_growth,
+        })
+    }
+
+    fn update(&mut self, board: &Board) {
+        let new_head = self.next_head(board);
+
+        // Apply the turn buffered since the last move
+        if let Some(direction) = self.queued_direction.take() {
+            self.direction = direction;
+        }
+
+        // Add new head
+        self.push_head(new_head);
+
+        // Remove tail if not growing
+        if self.pending_growth == 0 {
+            self.pop_tail();
+        } else {
+            self.pending_growth -= 1;
+        }
+    }
+
+    fn push_head(&mut self, head: Position) {
+        self.body.push_front(head);
+        #[cfg(feature = "occupancy-bitset")]
+        self.occupied.insert(head);
+    }
+
+    fn pop_tail(&mut self) {
+        if let Some(_tail) = self.body.pop_back() {
+            #[cfg(feature = "occupancy-bitset")]
+            self.occupied.remove(_tail);
+        }
+    }
+
+    // Called after the body is changed anywhere but its two ends
+    fn body_changed(&mut self) {
+        #[cfg(feature = "occupancy-bitset")]
+        {
+            self.occupied = Occupancy::from_cells(&self.body);
+        }
+    }
+
+    // Whether any segment is on `position`
+    #[cfg(feature = "occupancy-bitset")]
+    fn contains(&self, position: Position) -> bool {
+        self.occupied.contains(position)
+    }
+
+    #[cfg(not(feature = "occupancy-bitset"))]
+    fn contains(&self, position: Position) -> bool {
+        self.body.contains(&position)
+    }
+
+    // Whether another segment shares the head's cell
+    #[cfg(feature = "occupancy-bitset")]
+    fn head_on_body(&self) -> bool {
+        self.occupied.is_stacked(self.body[0])
+    }
+
+    #[cfg(not(feature = "occupancy-bitset"))]
+    fn head_on_body(&self) -> bool {
+        let head = self.body[0];
+        self.body.iter().skip(1).any(|segment| *segment == head)
+    }
+
+    // Where the head will be after the next move, taking any queued turn
+    // into account
+    fn next_head(&self, board: &Board) -> Position {
+        let head = self.body[0];
+        match self.queued_direction.unwrap_or(self.direction) {
+            Direction::Up => Position {
+                x: head.x,
+                y: head.y.saturating_sub(1),
+            },
+            Direction::Down => Position {
+                x: head.x,
+                y: (head.y + 1).min(board.height - 1),
+            },
+            Direction::Left => Position {
+                x: head.x.saturating_sub(1),
+                y: head.y,
+            },
+            Direction::Right => Position {
+                x: (head.x + 1).min(board.width - 1),
+                y: head.y,
+            },
+        }
+    }
+
+    /// The body from head to tail. Moving the snake pushes a new head on the
+    /// front and pops the tail off the back, both in constant time.
+    pub fn body(&self) -> &VecDeque<Position> {
+        &self.body
+    }
+
+    /// The direction the snake moved in on its last step.
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// How many more moves the snake will grow on before its length settles.
+    pub fn pending_growth(&self) -> usize {
+        self.pending_growth
+    }
+
+    /// The turn that will apply on the next tick, if one has been accepted
+    /// since the last step.
+    pub fn queued_direction(&self) -> Option<Direction> {
+        self.queued_direction
+    }
+
+    // Buffer a turn for the next move. Turns are checked against the current
+    // heading rather than an earlier queued turn, so two quick presses can't
+    // reverse the snake into itself within one tick; the later press wins,
+    // and pressing the current heading takes back a queued turn. Returns
+    // true if the queued turn changed, false if the press was a reverse that
+    // `rule` ignores or the snake was already heading that way with nothing
+    // queued.
+    fn change_direction(&mut self, new_direction: Direction, rule: ReverseRule) -> bool {
+        match rule {
+            ReverseRule::Ignore if new_direction == self.direction.opposite() => false,
+            _ if self.direction == new_direction => self.queued_direction.take().is_some(),
+            _ => {
+                self.queued_direction = Some(new_direction);
+                true
+            }
+        }
+    }
+
+    fn grow(&mut self) {
+        self.pending_growth += 1;
+    }
+
+    // Drop the back half of the body, rounding in the snake's favor so a
+    // one-cell snake keeps its only cell
+    fn split(&mut self) {
+        self.body.truncate(self.body.len().div_ceil(2));
+        self.body_changed();
+    }
+
+    // Whether the head, after a move, is on a wall or, unless `ghost` is
+    // set, any other segment.
+    //
+    // A reverse (only possible under ReverseRule::Collide) sends the head
+    // back onto the cell the neck was on. What happens then depends only on
+    // length, since the tail leaves its cell on the same move:
+    // - length 1: there is no neck, so the snake simply turns around;
+    // - length 2, not growing: the neck was the tail and has moved off, so
+    //   the snake turns around unharmed, head and tail swapping places;
+    // - length 2 while growing, or length 3+: the neck is still there and
+    //   the move is a collision.
+    fn check_collision(&self, board: &Board, ghost: bool) -> Option<CollisionKind> {
+        let head = self.body[0];
+
+        // Check if head hits the walls
+        if board.is_wall(head) {
+            return Some(CollisionKind::Wall);
+        }
+
+        if ghost {
+            return None;
+        }
+
+        // Check if head hits the body
+        if self.head_on_body() {
+            return Some(CollisionKind::Body);
+        }
+
+        None
+    }
+}
+
+/// The kinds of food that can appear on the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FoodKind {
+    /// Grows the snake by one cell and scores the food value.
+    Normal,
+    /// Like `Normal`, and also lets the snake pass through its own body for
+    /// `GameConfig::ghost_ticks` ticks. Walls stay deadly.
+    Ghost,
+    /// Scores the food value but cuts the snake in half instead of growing
+    /// it, keeping the head half. A one-cell snake stays as it is.
+    Split,
+}
+
+#[derive(Clone, Debug)]
+pub struct Food {
+    position: Position,
+    kind: FoodKind,
+    // Tick the food appeared on, which its value decays from
+    spawned_tick: u64,
+}
+
+impl Food {
+    fn new() -> Self {
+        Food {
+            position: Position { x: 0, y: 0 },
+            kind: FoodKind::Normal,
+            spawned_tick: 0,
+        }
+    }
+
+    // Place the food on a random cell free of the snake and the `others`
+    // already on the board, returning false if there is none. Uniform spawns
+    // switch approach once the snake and food cover half the playable cells,
+    // where random guesses start to miss more often than they hit.
+    fn spawn_with_rng(
+        &mut self,
+        snake: &Snake,
+        others: &[Position],
+        board: &Board,
+        strategy: SpawnStrategy,
+        rng: &mut impl Rng,
+    ) -> bool {
+        if strategy == SpawnStrategy::CenterBiased {
+            self.spawn_center_biased(snake, others, board, rng)
+        } else if snake.body.len() + others.len() > board.playable_cells() / 2 {
+            self.spawn_late_game(snake, others, board, rng)
+        } else {
+            self.spawn_early_game(snake, others, board, rng)
+        }
+    }
+
+    // Guess random cells, falling back to listing the free ones if
+    // EARLY_SPAWN_ATTEMPTS guesses all miss, which keeps a board crowded by
+    // walls from spinning here
+    fn spawn_early_game(
+        &mut self,
+        snake: &Snake,
+        others: &[Position],
+        board: &Board,
+        rng: &mut impl Rng,
+    ) -> bool {
+        for _ in 0..EARLY_SPAWN_ATTEMPTS {
+            let x = rng.random_range(1..board.width - 1);
+            let y = rng.random_range(1..board.height - 1);
+
+            // Make sure food doesn't spawn on a wall, the snake or other food
+            let position = Position { x, y };
+            if is_free(position, snake, others, board) {
+                self.position = position;
+                return true;
+            }
+        }
+        self.spawn_late_game(snake, others, board, rng)
+    }
+
+    fn spawn_late_game(
+        &mut self,
+        snake: &Snake,
+        others: &[Position],
+        board: &Board,
+        rng: &mut impl Rng,
+    ) -> bool {
+        match free_cells(snake, others, board).choose(rng) {
+            Some(i) => {
+                self.position = *i;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Weight each free cell by its distance to the nearest wall, so cells
+    // next to the border are the least likely and the center the most
+    fn spawn_center_biased(
+        &mut self,
+        snake: &Snake,
+        others: &[Position],
+        board: &Board,
+        rng: &mut impl Rng,
+    ) -> bool {
+        let allowed_spawns = free_cells(snake, others, board);
+        let wall_distance = |position: &Position| {
+            position
+                .x
+                .min(board.width - 1 - position.x)
+                .min(position.y)
+                .min(board.height - 1 - position.y)
+        };
+        match allowed_spawns.choose_weighted(rng, wall_distance) {
+            Ok(position) => {
+                self.position = *position;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+// Whether food can go on `position`: not on a wall, the snake or `others`
+fn is_free(position: Position, snake: &Snake, others: &[Position], board: &Board) -> bool {
+    !board.is_wall(position) && !snake.contains(position) && !others.contains(&position)
+}
+
+// Every cell food can go on, column by column
+fn free_cells(snake: &Snake, others: &[Position], board: &Board) -> Vec<Position> {
+    let mut cells = Vec::with_capacity(board.width * board.height);
+    for x in 1..board.width - 1 {
+        for y in 1..board.height - 1 {
+            let position = Position { x, y };
+            if is_free(position, snake, others, board) {
+                cells.push(position);
+            }
+        }
+    }
+    cells
+}
+
+/// A running game.
+///
+/// Cloning a game copies all of its state: board, snake, food, score, tick
+/// count and input log, plus the time of the last step. The clone is fully
+/// independent, so it can be stepped speculatively and thrown away without
+/// affecting the original. Because the step timer is copied as-is, a clone
+/// made mid-interval steps at the same moment the original would.
+#[derive(Clone, Debug)]
+pub struct Game {
+    board: Board,
+    start_board: Board,
+    // The snake each game starts with
+    start_snake: Snake,
+    initial_food: Vec<Position>,
+    food_sequence: Vec<Position>,
+    // How far through `food_sequence` spawning has got
+    sequence_index: usize,
+    adaptive_grid: bool,
+    key_bindings: KeyBindings,
+    mirror_horizontal: bool,
+    mirror_vertical: bool,
+    reverse_rule: ReverseRule,
+    snake: Snake,
+    food: Vec<Food>,
+    max_food: usize,
+    // Whether `food` must be eaten front to back. Food is only ever pushed
+    // on the end, so the front is always the oldest piece.
+    ordered_food: bool,
+    food_regen_ticks: u64,
+    // Ticks since the food on the board last dropped below `max_food` or a
+    // piece last regrew
+    regen_timer: u64,
+    score: u32,
+    start_score: u32,
+    survival_points: u32,
+    food_value: FoodValue,
+    food_scorer: Option<FoodScorer>,
+    food_bag: FoodBag,
+    ghost_duration: u64,
+    // Upcoming ticks on which the snake can pass through itself
+    ghost_ticks: u64,
+    // Kept apart from `rng` for the same reason as `autopilot_rng`: the
+    // food mix never shifts where food appears
+    food_bag_rng: ChaCha8Rng,
+    spawn_strategy: SpawnStrategy,
+    food_avoids_tail: bool,
+    high_score: Option<u32>,
+    // Whether the game that just ended beat `high_score`
+    new_high_score: bool,
+    game_over: bool,
+    won: bool,
+    win_condition: Option<WinCondition>,
+    autopilot: Option<AutopilotStrategy>,
+    // Strategy the autopilot uses when toggled on mid-game
+    autopilot_strategy: AutopilotStrategy,
+    // Whether the attract demo is playing
+    attract: bool,
+    auto_restart: Option<Duration>,
+    // When `update` first saw the current game over, for `auto_restart`
+    ended_at: Option<Instant>,
+    // Whether the autopilot has steered at any point this game, which keeps
+    // the score off the high score
+    assisted: bool,
+    seed: u64,
+    restart_seed: RestartSeed,
+    rng: ChaCha8Rng,
+    // Kept apart from `rng` so autopilot tie-breaks never shift food
+    // placement, letting a recorded autopilot run replay as plain input
+    autopilot_rng: ChaCha8Rng,
+    theme: Theme,
+    background: Option<Background>,
+    // `background` painted for the current theme, copied into each frame
+    backdrop: Option<Arc<[u32]>>,
+    border_style: BorderStyle,
+    random_colors: bool,
+    invincible: bool,
+    zen: bool,
+    scoring_zone: Option<ScoringZone>,
+    drift: Option<Drift>,
+    // Whether the snake has been steered since the current last pushed it
+    steered: bool,
+    zone: Option<Zone>,
+    // Whether the head was inside the zone after the last step
+    in_zone: bool,
+    // Kept apart from `rng` so zones never shift food placement
+    zone_rng: ChaCha8Rng,
+    wall_grace: bool,
+    // Whether the last tick was held back at a wall, so the next one isn't
+    holding_at_wall: bool,
+    show_legend: bool,
+    // Index into ZOOM_STEPS for the camera on boards too big to show whole
+    zoom: usize,
+    show_danger: bool,
+    animate_growth: bool,
+    show_particles: bool,
+    particles: Vec<Particle>,
+    // Whether the tail stayed put on the last step because the snake grew
+    tail_grew: bool,
+    // The highlighted pause menu entry while paused, `None` while playing
+    pause_menu: Option<PauseChoice>,
+    // The highlighted row while the options screen is open over the pause
+    // menu
+    options_row: Option<OptionsRow>,
+    // Settings changed on the options screen, waiting for the next new game
+    pending_options: Option<GameOptions>,
+    quit_requested: bool,
+    last_update: Instant,
+    refresh_rate: Duration,
+    vertical_refresh_rate: Duration,
+    ticks: u64,
+    input_log: Vec<(u64, Direction)>,
+    // Snapshot the player can jump back to for practicing a tricky spot,
+    // kept across deaths and restarts until cleared
+    practice_mark: Option<Box<Game>>,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new(GameConfig::default())
+    }
+}
+
+impl Game {
+    pub fn new(config: GameConfig) -> Self {
+        let board = config.board;
+        // Invalid start positions are caught by `GameConfig::validate`; one
+        // that slips through is moved inside the walls
+        let start_position = config
+            .start
+            .map_or(board.center(), |start| board.clamp_to_interior(start));
+        let seed = config.seed.unwrap_or_else(|| rand::rng().random());
+        let warmup_growth = if config.zen { 0 } else { config.warmup_growth };
+        let mut start_snake = config
+            .snake
+            .unwrap_or_else(|| Snake::new(start_position, warmup_growth));
+        if config.zen {
+            start_snake.pending_growth = 0;
+        }
+        let mut game = Game {
+            board: board.clone(),
+            start_board: board,
+            snake: start_snake.clone(),
+            start_snake,
+            initial_food: config.initial_food,
+            food_sequence: config.food_sequence,
+            sequence_index: 0,
+            adaptive_grid: config.adaptive_grid,
+            key_bindings: config.key_bindings,
+            mirror_horizontal: config.mirror_horizontal,
+            mirror_vertical: config.mirror_vertical,
+            reverse_rule: config.reverse_rule,
+            food: Vec::new(),
+            max_food: config.max_food,
+            ordered_food: config.ordered_food,
+            food_regen_ticks: config.food_regen_ticks,
+            regen_timer: 0,
+            score: config.start_score,
+            start_score: config.start_score,
+            survival_points: config.survival_points,
+            food_value: config.food_value,
+            food_scorer: config.food_scorer,
+            food_bag: FoodBag::new(&config.food_mix),
+            ghost_duration: config.ghost_ticks,
+            ghost_ticks: 0,
+            food_bag_rng: ChaCha8Rng::seed_from_u64(seed),
+            spawn_strategy: config.spawn_strategy,
+            food_avoids_tail: config.food_avoids_tail,
+            high_score: config.high_score,
+            new_high_score: false,
+            game_over: false,
+            won: false,
+            win_condition: config.win_condition,
+            autopilot: config.autopilot,
+            autopilot_strategy: config.autopilot.unwrap_or_default(),
+            attract: config.attract,
+            auto_restart: config.auto_restart,
+            ended_at: None,
+            assisted: config.autopilot.is_some() || config.attract,
+            seed,
+            restart_seed: config.restart_seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            autopilot_rng: ChaCha8Rng::seed_from_u64(seed),
+            theme: config.theme,
+            background: config.background,
+            backdrop: None,
+            border_style: config.border_style,
+            random_colors: config.random_colors,
+            invincible: config.invincible,
+            zen: config.zen,
+            scoring_zone: config.scoring_zone,
+            drift: config.drift,
+            steered: false,
+            zone: None,
+            in_zone: false,
+            zone_rng: ChaCha8Rng::seed_from_u64(seed),
+            wall_grace: config.wall_grace,
+            holding_at_wall: false,
+            show_legend: false,
+            zoom: DEFAULT_ZOOM,
+            show_danger: config.danger_hints,
+            animate_growth: config.animate_growth,
+            show_particles: config.particles,
+            particles: Vec::new(),
+            tail_grew: false,
+            pause_menu: None,
+            options_row: None,
+            pending_options: None,
+            quit_requested: false,
+            last_update: Instant::now(),
+            refresh_rate: Duration::from_millis(config.refresh_rate.max(MIN_REFRESH_RATE)),
+            vertical_refresh_rate: Duration::from_millis(
+                config
+                    .vertical_refresh_rate
+                    .unwrap_or(config.refresh_rate)
+                    .max(MIN_REFRESH_RATE),
+            ),
+            ticks: 0,
+            input_log: Vec::new(),
+            practice_mark: None,
+        };
+        game.place_starting_food();
+        game.move_zone();
+        if game.random_colors {
+            game.theme = seeded_theme(seed);
+        }
+        game.paint_backdrop();
+        game
+    }
+
+    /// The seed food placement is drawn from. Passing it back in through
+    /// `GameConfig::seed` reproduces the same sequence of food positions
+    /// for the same moves.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn snake(&self) -> &Snake {
+        &self.snake
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    /// Where every piece of food on the board is and what kind it is. The
+    /// list is a snapshot copied out of the game, so it doesn't change as the
+    /// game steps. Empty in zen mode.
+    pub fn food_positions(&self) -> Vec<(Position, FoodKind)> {
+        self.food
+            .iter()
+            .map(|food| (food.position, food.kind))
+            .collect()
+    }
+
+    /// The best score so far, including this game's once it has ended, or
+    /// `None` if high scores aren't tracked.
+    pub fn high_score(&self) -> Option<u32> {
+        self.high_score
+    }
+
+    /// Whether the game has ended with a score above the previous best.
+    pub fn is_new_high_score(&self) -> bool {
+        self.new_high_score
+    }
+
+    /// Number of steps taken since the game started.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Every accepted turn as `(tick, direction)`, in the order it was made.
+    /// Reversals the reverse rule ignores are not recorded, and neither are
+    /// repeats of the current direction unless they take back a turn queued
+    /// earlier in the same tick.
+    pub fn input_log(&self) -> &[(u64, Direction)] {
+        &self.input_log
+    }
+
+    /// Steers the snake toward `direction` on its next step, exactly as a
+    /// key press would (before mirroring). Returns whether the turn was
+    /// accepted; see `input_log` for which turns aren't.
+    pub fn turn(&mut self, direction: Direction) -> bool {
+        let accepted = self.snake.change_direction(direction, self.reverse_rule);
+        if accepted {
+            self.steered = true;
+            self.input_log.push((self.ticks, direction));
+        }
+        accepted
+    }
+
+    /// A fingerprint of where the game stands: the snake, the food, the
+    /// score, the tick count and whether the game has ended. Two games
+    /// played from the same seed and inputs have the same hash after every
+    /// step, on any machine and with any build, so comparing hashes at
+    /// checkpoints shows where a replay stops matching the original.
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = Fnv1a::default();
+        self.snake.body.hash(&mut hasher);
+        self.snake.direction.hash(&mut hasher);
+        self.snake.queued_direction.hash(&mut hasher);
+        self.snake.pending_growth.hash(&mut hasher);
+        for food in &self.food {
+            (food.position, food.kind, food.spawned_tick).hash(&mut hasher);
+        }
+        (self.score, self.ticks, self.ghost_ticks).hash(&mut hasher);
+        (self.game_over, self.won).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Whether the snake has died.
+    pub fn is_game_over(&self) -> bool {
+        self.game_over
+    }
+
+    /// Whether the game has been won.
+    pub fn is_won(&self) -> bool {
+        self.won
+    }
+
+    /// Throws away the current game and starts a new one built from
+    /// `config`, as if by `Game::new`, so changed options such as the board
+    /// size, speed or theme take effect without a new process. Unlike
+    /// restarting with R, nothing carries over, including the practice
+    /// mark. Rendering follows the new board size on the next frame.
+    pub fn reset_with_config(&mut self, config: GameConfig) {
+        *self = Game::new(config);
+    }
+
+    /// How much of the board the snake covers, from just above 0.0 for a
+    /// one-cell snake to exactly 1.0 when it fills every playable cell,
+    /// which wins the game.
+    pub fn fill_fraction(&self) -> f32 {
+        self.snake.body.len() as f32 / self.board.playable_cells() as f32
+    }
+
+    /// How many more ticks the snake can pass through its own body for,
+    /// after eating ghost food. Zero when it can't.
+    pub fn ghost_ticks(&self) -> u64 {
+        self.ghost_ticks
+    }
+
+    /// What the snake would hit if its next step went in `direction`, or
+    /// `None` if that step is safe. Nothing about the game changes, and the
+    /// answer uses the same rules as a real step: the tail counts as moving
+    /// out of the way unless the snake is growing, ghost food lets the head
+    /// pass the body, and an invincible snake never collides.
+    ///
+    /// `direction` is taken as given, so a reverse reports the neck even
+    /// under `ReverseRule::Ignore`, where the turn would be refused. A wall
+    /// is reported even when `wall_grace` would hold the snake for a tick
+    /// before it hits.
+    pub fn would_collide(&self, direction: Direction) -> Option<CollisionKind> {
+        if self.invincible {
+            return None;
+        }
+        let mut snake = self.snake.clone();
+        snake.queued_direction = Some(direction);
+        snake.update(&self.board);
+        snake.check_collision(&self.board, self.ghost_ticks > 0)
+    }
+
+    /// Steps the game if the refresh interval has passed, returning what
+    /// happened during the step (nothing if it wasn't time to step yet).
+    pub fn update(&mut self) -> Vec<TimedEvent> {
+        self.update_with(&mut NoTelemetry)
+    }
+
+    /// Like `update`, reporting the step to `telemetry` as it happens.
+    pub fn update_with(&mut self, telemetry: &mut impl Telemetry) -> Vec<TimedEvent> {
+        self.update_at(Instant::now(), telemetry)
+    }
+
+    /// Like `update_with`, taking the current time as `now` instead of
+    /// reading the clock, so step timing and auto-restart can be driven by
+    /// hand.
+    pub fn update_at(&mut self, now: Instant, telemetry: &mut impl Telemetry) -> Vec<TimedEvent> {
+        if self.attract && (self.game_over || self.won) {
+            self.restart();
+        }
+        if let Some(delay) = self.auto_restart.filter(|_| self.game_over || self.won) {
+            let ended_at = *self.ended_at.get_or_insert(now);
+            if now.saturating_duration_since(ended_at) >= delay {
+                self.restart();
+            }
+        }
+        if self.game_over || self.won || self.pause_menu.is_some() {
+            return Vec::new();
+        }
+
+        if now.saturating_duration_since(self.last_update) >= self.step_interval() {
+            self.last_update = now;
+            return self.tick(telemetry);
+        }
+        Vec::new()
+    }
+
+    // How long the next step waits, which depends on the axis the snake is
+    // about to move along. A turn queued since the last step already counts,
+    // so turning from a fast axis to a slow one delays the turn's own step.
+    fn step_interval(&self) -> Duration {
+        match self.snake.queued_direction.unwrap_or(self.snake.direction) {
+            Direction::Up | Direction::Down => self.vertical_refresh_rate,
+            Direction::Left | Direction::Right => self.refresh_rate,
+        }
+    }
+
+    /// Steps the game once right away, ignoring the refresh interval. Does
+    /// nothing once the game has ended.
+    pub fn step(&mut self) -> Vec<TimedEvent> {
+        self.step_with(&mut NoTelemetry)
+    }
+
+    /// Like `step`, reporting the step to `telemetry` as it happens.
+    pub fn step_with(&mut self, telemetry: &mut impl Telemetry) -> Vec<TimedEvent> {
+        if self.game_over || self.won {
+            return Vec::new();
+        }
+        self.tick(telemetry)
+    }
+
+    /// Runs the game to the end without a clock, applying each `(tick,
+    /// direction)` input just before step `tick + 1`, the same way
+    /// `input_log` records them. Never returns if the game can't end, so
+    /// prefer `simulate_bounded` for untrusted inputs.
+    pub fn simulate(&mut self, inputs: &[(u64, Direction)]) {
+        self.simulate_bounded(inputs, u64::MAX);
+    }
+
+    /// Like `simulate`, but stops after at most `max_ticks` steps. Returns
+    /// `true` if the game ended (died or won) and `false` if it was still
+    /// running when the cap was hit.
+    pub fn simulate_bounded(&mut self, inputs: &[(u64, Direction)], max_ticks: u64) -> bool {
+        let start = self.ticks;
+        let mut pending = inputs
+            .iter()
+            .skip_while(|(tick, _)| *tick < start)
+            .peekable();
+        for _ in 0..max_ticks {
+            if self.game_over || self.won {
+                break;
+            }
+            while let Some((_, direction)) = pending.next_if(|(tick, _)| *tick == self.ticks) {
+                self.turn(*direction);
+            }
+            self.tick(&mut NoTelemetry);
+        }
+        self.game_over || self.won
+    }
+
+    // Advance the game by exactly one step
+    fn tick(&mut self, telemetry: &mut impl Telemetry) -> Vec<TimedEvent> {
+        let events = self
+            .advance(telemetry)
+            .into_iter()
+            .map(|event| TimedEvent {
+                tick: self.ticks,
+                event,
+            })
+            .collect();
+        telemetry.on_tick(self);
+        events
+    }
+
+    fn advance(&mut self, telemetry: &mut impl Telemetry) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        self.move_particles();
+
+        let pilot = self
+            .autopilot
+            .or(self.attract.then_some(self.autopilot_strategy));
+        if let Some(strategy) = pilot {
+            // Food that isn't next in line is as deadly as a wall
+            let avoid: Vec<Position> = if self.ordered_food {
+                self.food.iter().skip(1).map(|food| food.position).collect()
+            } else {
+                Vec::new()
+            };
+            let next = autopilot::next_direction(
+                strategy,
+                &self.board,
+                &self.snake,
+                self.nearest_food(),
+                &avoid,
+                &mut self.autopilot_rng,
+            );
+            if let Some(direction) = next {
+                self.turn(direction);
+            }
+        }
+
+        // The current only turns a snake nobody has steered since its last
+        // push, and never turns it straight around
+        if let Some(drift) = self.drift {
+            if (self.ticks + 1).is_multiple_of(drift.every_ticks) {
+                if !self.steered {
+                    self.snake
+                        .change_direction(drift.direction, ReverseRule::Ignore);
+                }
+                self.steered = false;
+            }
+        }
+
+        if self.wall_grace && !self.invincible {
+            let heading_into_wall = self.board.is_wall(self.snake.next_head(&self.board));
+            if heading_into_wall && !self.holding_at_wall {
+                self.holding_at_wall = true;
+                self.ticks += 1;
+                return events;
+            }
+            self.holding_at_wall = false;
+        }
+
+        self.tail_grew = self.snake.pending_growth > 0;
+        self.snake.update(&self.board);
+        if self.invincible {
+            self.snake.body[0] = self.board.clamp_to_interior(self.snake.body[0]);
+            self.snake.body_changed();
+        }
+        self.ticks += 1;
+        let ghost = self.ghost_ticks > 0;
+        self.ghost_ticks = self.ghost_ticks.saturating_sub(1);
+
+        // Check if snake ate food. Food eaten out of turn ends the run, or is
+        // passed over when the snake is invincible.
+        let head = self.snake.body[0];
+        let index = self.food.iter().position(|food| food.position == head);
+        let out_of_order = self.ordered_food && index.is_some_and(|index| index > 0);
+        if out_of_order && !self.invincible {
+            self.game_over = true;
+            events.push(GameEvent::Died);
+            self.record_high_score();
+            telemetry.on_death(self);
+            return events;
+        }
+        let eaten = index.filter(|_| !out_of_order).map(|index| {
+            let food = self.food.remove(index);
+            (food.kind, self.score_food(&food))
+        });
+        if let Some((kind, points)) = eaten {
+            events.push(GameEvent::Ate(head));
+            self.burst_particles(head, self.food_color(kind));
+            match kind {
+                FoodKind::Normal => self.snake.grow(),
+                FoodKind::Ghost => {
+                    self.snake.grow();
+                    self.ghost_ticks = self.ghost_duration;
+                }
+                FoodKind::Split => self.snake.split(),
+            }
+            self.award(i32::try_from(points).unwrap_or(i32::MAX));
+        }
+        let room_left = self.regenerate_food();
+        if let Some((kind, points)) = eaten {
+            telemetry.on_eat(self, head, kind, points);
+        }
+
+        // Check for collisions
+        if !self.invincible && self.snake.check_collision(&self.board, ghost).is_some() {
+            self.game_over = true;
+            events.push(GameEvent::Died);
+            self.record_high_score();
+            telemetry.on_death(self);
+            return events;
+        }
+
+        if !self.zen {
+            self.award(i32::try_from(self.survival_points).unwrap_or(i32::MAX));
+        }
+
+        if let Some(zone) = self.zone {
+            let inside = zone.contains(self.snake.body[0]);
+            if inside && !self.in_zone {
+                events.push(GameEvent::EnteredZone);
+                let bonus = self.scoring_zone.map_or(0, |zone| zone.bonus);
+                self.award(i32::try_from(bonus).unwrap_or(i32::MAX));
+            }
+            self.in_zone = inside;
+            if self
+                .scoring_zone
+                .is_some_and(|zone| self.ticks.is_multiple_of(zone.shift_ticks))
+            {
+                self.move_zone();
+            }
+        }
+
+        // Check for a win, either by filling the board or reaching the goal.
+        // Running out of room for food with none left to eat is a full
+        // board too, even when a ghost snake overlapping itself is shorter
+        // than the board.
+        let nothing_to_eat = !room_left && self.food.is_empty();
+        if self.snake.body.len() >= self.board.playable_cells()
+            || nothing_to_eat
+            || self.reached_win_condition()
+        {
+            self.won = true;
+            events.push(GameEvent::Won);
+            self.record_high_score();
+            telemetry.on_win(self);
+            return events;
+        }
+
+        if self.adaptive_grid {
+            self.expand_board_if_crowded();
+        }
+        events
+    }
+
+    // Put the scoring zone somewhere new, if there is one. A zone that lands
+    // on the head doesn't count as the head entering it.
+    fn move_zone(&mut self) {
+        let Some(config) = self.scoring_zone else {
+            return;
+        };
+        let zone = Zone::random(&self.board, config.size, &mut self.zone_rng);
+        self.in_zone = zone.contains(self.snake.body[0]);
+        self.zone = Some(zone);
+    }
+
+    // Add one piece of food, returning false if there was nowhere to put it
+    fn spawn_food(&mut self) -> bool {
+        let mut others: Vec<Position> = self.food.iter().map(|food| food.position).collect();
+        let mut food = Food::new();
+        if let Some(position) = self.next_in_sequence(&others) {
+            food.position = position;
+        } else {
+            if self.food_avoids_tail {
+                // Keep the tail's free neighbors clear by treating them as taken,
+                // as long as that leaves a free cell somewhere else
+                let tail = self.snake.body[self.snake.body.len() - 1];
+                let near_tail: Vec<Position> = Direction::ALL
+                    .into_iter()
+                    .map(|direction| tail.neighbor(direction))
+                    .filter(|cell| {
+                        !self.board.is_wall(*cell)
+                            && !self.snake.contains(*cell)
+                            && !others.contains(cell)
+                    })
+                    .collect();
+                let free = self
+                    .board
+                    .playable_cells()
+                    .saturating_sub(self.snake.body.len() + others.len());
+                if free > near_tail.len() {
+                    others.extend(near_tail);
+                }
+            }
+            if !food.spawn_with_rng(
+                &self.snake,
+                &others,
+                &self.board,
+                self.spawn_strategy,
+                &mut self.rng,
+            ) {
+                return false;
+            }
+        }
+        food.kind = self.food_bag.draw(&mut self.food_bag_rng);
+        food.spawned_tick = self.ticks;
+        self.food.push(food);
+        true
+    }
+
+    // Throw PARTICLES_PER_BURST sparks out of `cell` at evenly spread angles,
+    // dropping the oldest sparks beyond MAX_PARTICLES
+    fn burst_particles(&mut self, cell: Position, color: u32) {
+        if !self.show_particles {
+            return;
+        }
+        for i in 0..PARTICLES_PER_BURST {
+            let angle = i as f32 * std::f32::consts::TAU / PARTICLES_PER_BURST as f32;
+            self.particles.push(Particle {
+                x: cell.x as f32 + 0.5,
+                y: cell.y as f32 + 0.5,
+                dx: angle.cos() * PARTICLE_SPEED,
+                dy: angle.sin() * PARTICLE_SPEED,
+                ticks_left: PARTICLE_TICKS,
+                color,
+            });
+        }
+        let excess = self.particles.len().saturating_sub(MAX_PARTICLES);
+        self.particles.drain(..excess);
+    }
+
+    fn move_particles(&mut self) {
+        for particle in &mut self.particles {
+            particle.x += particle.dx;
+            particle.y += particle.dy;
+            particle.ticks_left -= 1;
+        }
+        self.particles.retain(|particle| particle.ticks_left > 0);
+    }
+
+    // The next free cell in the food sequence, if any are left
+    fn next_in_sequence(&mut self, others: &[Position]) -> Option<Position> {
+        while let Some(&position) = self.food_sequence.get(self.sequence_index) {
+            self.sequence_index += 1;
+            if !self.snake.contains(position) && !others.contains(&position) {
+                return Some(position);
+            }
+        }
+        None
+    }
+
+    // Put the initial food down, then fill the board up at random
+    fn place_starting_food(&mut self) {
+        if !self.zen {
+            for position in &self.initial_food {
+                let mut food = Food::new();
+                food.position = *position;
+                self.food.push(food);
+            }
+        }
+        self.fill_food();
+    }
+
+    // Spawn food until the board holds `max_food`, returning false if it
+    // ran out of room first
+    fn fill_food(&mut self) -> bool {
+        while !self.zen && self.food.len() < self.max_food {
+            if !self.spawn_food() {
+                return false;
+            }
+        }
+        true
+    }
+
+    // Called every tick: grow one piece of food back each `food_regen_ticks`
+    // ticks while the board is short, or refill it at once when that's zero.
+    // Returns false if food was due but there was nowhere to put it.
+    fn regenerate_food(&mut self) -> bool {
+        if self.food_regen_ticks == 0 {
+            return self.fill_food();
+        }
+        if self.zen || self.food.len() >= self.max_food {
+            self.regen_timer = 0;
+            return true;
+        }
+        self.regen_timer += 1;
+        if self.regen_timer >= self.food_regen_ticks {
+            self.regen_timer = 0;
+            return self.spawn_food();
+        }
+        true
+    }
+
+    // The food closest to the head, for the autopilot to aim at, or the one
+    // that must be eaten next when food is eaten in order
+    fn nearest_food(&self) -> Option<Position> {
+        if self.ordered_food {
+            return self.food.first().map(|food| food.position);
+        }
+        let head = self.snake.body[0];
+        self.food
+            .iter()
+            .map(|food| food.position)
+            .min_by_key(|food| head.manhattan_distance(*food))
+    }
+
+    // What `food` is worth if eaten now
+    fn food_points(&self, food: &Food) -> u32 {
+        self.food_value
+            .points(self.ticks.saturating_sub(food.spawned_tick))
+    }
+
+    // What eating `food` scores, by the custom scorer if there is one
+    fn score_food(&self, food: &Food) -> u32 {
+        match &self.food_scorer {
+            Some(FoodScorer(score)) => score(self, food.kind),
+            None => self.food_points(food),
+        }
+    }
+
+    // Add `points` to the score, which may be negative to take points away.
+    // The score saturates at zero and u32::MAX rather than wrapping.
+    fn award(&mut self, points: i32) {
+        self.score = self.score.saturating_add_signed(points);
+    }
+
+    // Called once the game ends
+    fn record_high_score(&mut self) {
+        if self.assisted {
+            return;
+        }
+        if let Some(best) = self.high_score {
+            if self.score > best {
+                self.high_score = Some(self.score);
+                self.new_high_score = true;
+            }
+        }
+    }
+
+    fn reached_win_condition(&self) -> bool {
+        match self.win_condition {
+            Some(WinCondition::TargetScore(target)) => self.score >= target,
+            Some(WinCondition::TargetLength(target)) => self.snake.body.len() >= target,
+            None => false,
+        }
+    }
+
+    // Grow the board by ADAPTIVE_GRID_STEP cells on every side once the snake
+    // covers ADAPTIVE_GRID_FILL of the playable area, as long as the larger
+    // board can still be drawn with cells of at least MIN_CELL_SIZE pixels.
+    // Everything on the board is shifted by the same offset so the snake
+    // keeps its place relative to the walls.
+    fn expand_board_if_crowded(&mut self) {
+        let fill = self.snake.body.len() as f32 / self.board.playable_cells() as f32;
+        if fill < ADAPTIVE_GRID_FILL {
+            return;
+        }
+
+        let expanded = Board::new(
+            self.board.width + 2 * ADAPTIVE_GRID_STEP,
+            self.board.height + 2 * ADAPTIVE_GRID_STEP,
+        );
+        if expanded.cell_size() < MIN_CELL_SIZE {
+            return;
+        }
+
+        for segment in self.snake.body.iter_mut() {
+            segment.x += ADAPTIVE_GRID_STEP;
+            segment.y += ADAPTIVE_GRID_STEP;
+        }
+        self.snake.body_changed();
+        for food in self.food.iter_mut() {
+            food.position.x += ADAPTIVE_GRID_STEP;
+            food.position.y += ADAPTIVE_GRID_STEP;
+        }
+        if let Some(zone) = self.zone.as_mut() {
+            zone.origin.x += ADAPTIVE_GRID_STEP;
+            zone.origin.y += ADAPTIVE_GRID_STEP;
+        }
+        self.board = expanded;
+    }
+
+    pub fn handle_input(&mut self, window: &Window) {
+        if self.key_bindings.quit_pressed(window) {
+            self.quit_requested = true;
+            return;
+        }
+        if self.attract {
+            if !window.get_keys_pressed(minifb::KeyRepeat::No).is_empty() {
+                self.attract = false;
+                self.restart();
+            }
+            return;
+        }
+        if window.is_key_pressed(self.key_bindings.menu, minifb::KeyRepeat::No) {
+            self.return_to_menu();
+            return;
+        }
+        if window.is_key_pressed(Key::L, minifb::KeyRepeat::No) {
+            self.show_legend = !self.show_legend;
+        }
+        if window.is_key_pressed(Key::H, minifb::KeyRepeat::No) {
+            self.show_danger = !self.show_danger;
+        }
+        if window.is_key_pressed(Key::T, minifb::KeyRepeat::No) {
+            self.cycle_theme();
+        }
+        self.handle_zoom_input(window);
+        if window.is_key_pressed(Key::Backspace, minifb::KeyRepeat::No) {
+            self.return_to_mark();
+        }
+        if window.is_key_pressed(Key::C, minifb::KeyRepeat::No) {
+            self.clear_mark();
+        }
+
+        if self.game_over || self.won {
+            if window.is_key_pressed(self.key_bindings.restart, minifb::KeyRepeat::No) {
+                self.restart();
+            }
+            return;
+        }
+
+        if let Some(row) = self.options_row {
+            self.handle_options_input(window, row);
+            return;
+        }
+        if window.is_key_pressed(self.key_bindings.pause, minifb::KeyRepeat::No) {
+            if self.is_paused() {
+                self.resume();
+            } else {
+                self.pause();
+            }
+            return;
+        }
+        if let Some(selected) = self.pause_menu {
+            for direction in self.key_bindings.pressed_directions(window) {
+                match direction {
+                    Direction::Up => {
+                        self.pause_menu = Some(menu_moved(&PauseChoice::ALL, selected, -1))
+                    }
+                    Direction::Down => {
+                        self.pause_menu = Some(menu_moved(&PauseChoice::ALL, selected, 1))
+                    }
+                    Direction::Left | Direction::Right => {}
+                }
+            }
+            if window.is_key_pressed(Key::Enter, minifb::KeyRepeat::No) {
+                self.choose(selected);
+            }
+            return;
+        }
+
+        if window.is_key_pressed(Key::M, minifb::KeyRepeat::No) {
+            self.set_mark();
+        }
+        if window.is_key_pressed(self.key_bindings.toggle_autopilot, minifb::KeyRepeat::No) {
+            self.toggle_autopilot();
+        }
+        if self.autopilot.is_some() {
+            return;
+        }
+
+        for direction in self.key_bindings.pressed_directions(window) {
+            self.turn(direction.mirrored(self.mirror_horizontal, self.mirror_vertical));
+        }
+    }
+
+    /// Whether the game is paused with the pause menu open.
+    pub fn is_paused(&self) -> bool {
+        self.pause_menu.is_some()
+    }
+
+    /// Stops the clock and opens the pause menu with `Resume` selected.
+    /// Finished games can't be paused.
+    pub fn pause(&mut self) {
+        if !self.game_over && !self.won {
+            self.pause_menu = Some(PauseChoice::Resume);
+        }
+    }
+
+    /// Closes the pause menu and options screen. The step interval starts
+    /// over, so the snake doesn't move the instant the game resumes.
+    pub fn resume(&mut self) {
+        self.options_row = None;
+        if self.pause_menu.take().is_some() {
+            self.last_update = Instant::now();
+        }
+    }
+
+    /// Acts on a pause menu entry. `Options` opens the options screen over
+    /// the menu; the others close the menu.
+    pub fn choose(&mut self, choice: PauseChoice) {
+        if choice == PauseChoice::Options {
+            if self.is_paused() {
+                self.options_row = Some(OptionsRow::Speed);
+            }
+            return;
+        }
+        self.resume();
+        match choice {
+            PauseChoice::Resume | PauseChoice::Options => {}
+            PauseChoice::Restart => self.restart(),
+            PauseChoice::Quit => self.quit_requested = true,
+        }
+    }
+
+    /// The settings the options screen shows: the ones changed there since
+    /// the last new game, and the current ones otherwise. Compare before and
+    /// after input to find out what to save.
+    pub fn options(&self) -> GameOptions {
+        self.pending_options.unwrap_or(GameOptions {
+            refresh_rate: self.refresh_rate.as_millis() as u64,
+            theme: self.theme,
+            wall_grace: self.wall_grace,
+        })
+    }
+
+    // Up and Down pick a row, Left and Right change its setting, and Enter
+    // on Back or the pause key returns to the pause menu
+    fn handle_options_input(&mut self, window: &Window, row: OptionsRow) {
+        let back = window.is_key_pressed(self.key_bindings.pause, minifb::KeyRepeat::No)
+            || (row == OptionsRow::Back
+                && window.is_key_pressed(Key::Enter, minifb::KeyRepeat::No));
+        if back {
+            self.options_row = None;
+            return;
+        }
+        for direction in self.key_bindings.pressed_directions(window) {
+            match direction {
+                Direction::Up => {
+                    self.options_row = Some(menu_moved(&OptionsRow::ALL, row, -1));
+                }
+                Direction::Down => {
+                    self.options_row = Some(menu_moved(&OptionsRow::ALL, row, 1));
+                }
+                Direction::Left => self.change_option(row, false),
+                Direction::Right => self.change_option(row, true),
+            }
+        }
+    }
+
+    // Step a setting to its next value, or its previous one if `forward` is
+    // false. Forward makes the snake faster.
+    fn change_option(&mut self, row: OptionsRow, forward: bool) {
+        let mut options = self.options();
+        match row {
+            OptionsRow::Speed => {
+                let current = options.refresh_rate;
+                let step = if forward {
+                    SPEED_STEPS.into_iter().find(|ms| *ms < current)
+                } else {
+                    SPEED_STEPS.into_iter().rev().find(|ms| *ms > current)
+                };
+                options.refresh_rate = step.unwrap_or(current);
+            }
+            OptionsRow::Theme => {
+                options.theme = if forward {
+                    options.theme.next_preset()
+                } else {
+                    options.theme.previous_preset()
+                };
+            }
+            OptionsRow::Walls => options.wall_grace = !options.wall_grace,
+            OptionsRow::Back => return,
+        }
+        self.pending_options = Some(options);
+    }
+
+    // Put settings from the options screen into effect, at the start of a
+    // new game
+    fn apply_pending_options(&mut self) {
+        let Some(options) = self.pending_options.take() else {
+            return;
+        };
+        let refresh_rate = Duration::from_millis(options.refresh_rate.max(MIN_REFRESH_RATE));
+        // Vertical moves keep a speed of their own if they were given one
+        if self.vertical_refresh_rate == self.refresh_rate {
+            self.vertical_refresh_rate = refresh_rate;
+        }
+        self.refresh_rate = refresh_rate;
+        self.wall_grace = options.wall_grace;
+        if options.theme != self.theme {
+            self.theme = options.theme;
+            self.random_colors = false;
+            self.paint_backdrop();
+        }
+    }
+
+    /// Whether the player pressed the quit key or picked `Quit` from the
+    /// pause menu, so the caller should close the game.
+    pub fn quit_requested(&self) -> bool {
+        self.quit_requested
+    }
+
+    // + and - (or the scroll wheel) step the camera zoom on large boards
+    fn handle_zoom_input(&mut self, window: &Window) {
+        let scroll = window.get_scroll_wheel().map_or(0.0, |(_, y)| y);
+        let zoom_in = [Key::Equal, Key::NumPadPlus]
+            .into_iter()
+            .any(|key| window.is_key_pressed(key, minifb::KeyRepeat::Yes));
+        let zoom_out = [Key::Minus, Key::NumPadMinus]
+            .into_iter()
+            .any(|key| window.is_key_pressed(key, minifb::KeyRepeat::Yes));
+        if zoom_in || scroll > 0.0 {
+            self.zoom = (self.zoom + 1).min(ZOOM_STEPS.len() - 1);
+        } else if zoom_out || scroll < 0.0 {
+            self.zoom = self.zoom.saturating_sub(1);
+        }
+    }
+
+    /// Abandons the current game for the title screen, with the attract demo
+    /// playing behind it until a key is pressed.
+    pub fn return_to_menu(&mut self) {
+        self.resume();
+        self.attract = true;
+        self.restart();
+    }
+
+    /// Whether the attract demo is still playing, before any key is pressed.
+    pub fn is_attract(&self) -> bool {
+        self.attract
+    }
+
+    /// Whether the autopilot is currently steering.
+    pub fn is_autopilot(&self) -> bool {
+        self.autopilot.is_some()
+    }
+
+    /// Hands steering to the autopilot, or back to the player, without
+    /// interrupting the game. The snake keeps its heading either way, so the
+    /// switch never causes a turn by itself. A game the autopilot has played
+    /// any part of doesn't count toward the high score.
+    pub fn toggle_autopilot(&mut self) {
+        self.autopilot = match self.autopilot {
+            Some(_) => None,
+            None => {
+                self.assisted = true;
+                Some(self.autopilot_strategy)
+            }
+        };
+    }
+
+    /// Remembers the game exactly as it is now, replacing any earlier mark,
+    /// so `return_to_mark` can bring it back for another attempt.
+    pub fn set_mark(&mut self) {
+        let mut mark = self.clone();
+        mark.practice_mark = None;
+        self.practice_mark = Some(Box::new(mark));
+    }
+
+    /// Whether a practice mark is set.
+    pub fn has_mark(&self) -> bool {
+        self.practice_mark.is_some()
+    }
+
+    /// Restores the game saved by `set_mark`, even after the snake has died.
+    /// The mark itself stays, so the same spot can be retried as often as
+    /// needed, and the current theme and legend are kept. A game that has
+    /// been rewound this way doesn't count toward the high score. Does
+    /// nothing if no mark is set.
+    pub fn return_to_mark(&mut self) {
+        let Some(mark) = self.practice_mark.take() else {
+            return;
+        };
+        let mut restored = (*mark).clone();
+        restored.practice_mark = Some(mark);
+        restored.theme = self.theme;
+        restored.backdrop = self.backdrop.clone();
+        restored.random_colors = self.random_colors;
+        restored.show_legend = self.show_legend;
+        restored.zoom = self.zoom;
+        restored.show_danger = self.show_danger;
+        restored.high_score = self.high_score;
+        restored.assisted = true;
+        restored.last_update = Instant::now();
+        *self = restored;
+    }
+
+    /// Forgets the practice mark.
+    pub fn clear_mark(&mut self) {
+        self.practice_mark = None;
+    }
+
+    /// The palette the game is currently drawn with.
+    pub fn theme(&self) -> Theme {
+        self.theme
+    }
+
+    // Switch to the next built-in palette. Picking one by hand turns off
+    // random colors so a restart doesn't replace it.
+    fn cycle_theme(&mut self) {
+        self.theme = self.theme.next_preset();
+        self.random_colors = false;
+        self.paint_backdrop();
+        if let Some(options) = self.pending_options.as_mut() {
+            options.theme = self.theme;
+        }
+    }
+
+    // Repaint the background for the current theme. Call after every theme
+    // change.
+    fn paint_backdrop(&mut self) {
+        self.backdrop = self
+            .background
+            .as_ref()
+            .map(|background| background.paint(&self.theme));
+    }
+
+    /// Renders the board as text, one line per row: `#` walls, `O` the head,
+    /// `o` the rest of the body, `*` food, `@` ghost food or `%` split food
+    /// (none in zen mode), `.` empty cells in the scoring zone and spaces
+    /// for other empty cells.
+    pub fn to_ascii(&self) -> String {
+        let mut ascii = String::with_capacity((self.board.width + 1) * self.board.height);
+        for y in 0..self.board.height {
+            for x in 0..self.board.width {
+                let position = Position { x, y };
+                let cell = if self.snake.body[0] == position {
+                    'O'
+                } else if self.snake.contains(position) {
+                    'o'
+                } else if let Some(food) = self.food.iter().find(|food| food.position == position) {
+                    match food.kind {
+                        FoodKind::Normal => '*',
+                        FoodKind::Ghost => '@',
+                        FoodKind::Split => '%',
+                    }
+                } else if self.board.is_wall(position) {
+                    '#'
+                } else if self.zone.is_some_and(|zone| zone.contains(position)) {
+                    '.'
+                } else {
+                    ' '
+                };
+                ascii.push(cell);
+            }
+            ascii.push('\n');
+        }
+        ascii
+    }
+
+    // While growth is animated, how much of the tail's cell to draw and the
+    // side it grows out from (the one touching the rest of the body), eased
+    // out over the step after the snake grew
+    fn tail_growth(&self) -> Option<(Direction, f32)> {
+        if !self.animate_growth || !self.tail_grew || self.game_over || self.won {
+            return None;
+        }
+        let body = &self.snake.body;
+        let tail = body[body.len() - 1];
+        let neck = body[body.len() - 2];
+        let toward = tail.direction_to(neck)?;
+        let progress = (self.last_update.elapsed().as_secs_f32()
+            / self.step_interval().as_secs_f32())
+        .min(1.0);
+        Some((toward, 1.0 - (1.0 - progress).powi(2)))
+    }
+
+    // Tint the cells around the head that would end the game if the snake
+    // moved into them. A reverse the rules would refuse is left out, since
+    // the player can't make that move anyway.
+    fn render_danger(&self, buffer: &mut [u32], viewport: &Viewport) {
+        let head = self.snake.body[0];
+        let refused = match self.reverse_rule {
+            ReverseRule::Ignore => Some(self.snake.direction.opposite()),
+            ReverseRule::Collide => None,
+        };
+        for direction in [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ] {
+            if Some(direction) != refused && self.would_collide(direction).is_some() {
+                viewport.tint_cell(buffer, head.neighbor(direction), DANGER_COLOR, DANGER_TINT);
+            }
+        }
+    }
+
+    /// Draws the game into a `WINDOW_WIDTH` x `WINDOW_HEIGHT` buffer. The
+    /// food is left out when `food_visible` is false, so callers can blink it
+    /// on their own clock independent of the game's tick rate.
+    pub fn render(&self, buffer: &mut [u32], food_visible: bool) {
+        let viewport = Viewport::new(&self.board, self.snake.body[0], self.zoom);
+
+        // Clear buffer
+        match &self.backdrop {
+            Some(backdrop) => buffer.copy_from_slice(backdrop),
+            None => buffer.fill(self.theme.background),
+        }
+
+        // Draw the scoring zone as a faint tint under everything else
+        if let Some(zone) = self.zone {
+            let tint = effects::blend(self.theme.background, self.theme.border, ZONE_TINT);
+            for position in viewport.cells().filter(|p| zone.contains(*p)) {
+                viewport.fill_cell(buffer, position, tint);
+            }
+        }
+
+        // Draw snake, see-through while it's a ghost
+        let snake_color = if self.ghost_ticks > 0 {
+            effects::blend(self.theme.snake, self.theme.background, GHOST_FADE)
+        } else {
+            self.theme.snake
+        };
+        for (i, segment) in self.snake.body.iter().enumerate() {
+            if i > 0 && i == self.snake.body.len() - 1 {
+                if let Some((toward, fraction)) = self.tail_growth() {
+                    viewport.fill_cell_part(buffer, *segment, toward, fraction, snake_color);
+                    continue;
+                }
+            }
+            viewport.fill_cell(buffer, *segment, snake_color);
+        }
+
+        // Draw food, labelled with its value while that value is decaying.
+        // Food eaten in order is numbered, with all but the next one faded.
+        if food_visible {
+            for (i, food) in self.food.iter().enumerate() {
+                let color = if self.ordered_food && i > 0 {
+                    effects::blend(
+                        self.food_color(food.kind),
+                        self.theme.background,
+                        ORDER_FADE,
+                    )
+                } else {
+                    self.food_color(food.kind)
+                };
+                viewport.fill_cell(buffer, food.position, color);
+                if self.ordered_food {
+                    self.render_food_order(buffer, &viewport, food.position, i + 1);
+                }
+                if self.food_value.decay > 0 {
+                    self.render_food_value(buffer, &viewport, food);
+                }
+            }
+        }
+
+        // Sparks stop moving when the game ends, so they'd hang in the air
+        if !self.game_over {
+            for particle in &self.particles {
+                particle.render(buffer, &viewport);
+            }
+        }
+
+        // Draw border
+        let border = self.theme.border;
+        let faded = effects::blend(border, self.theme.background, BORDER_PATTERN_FADE);
+        let half = (viewport.cell_size / 2).max(1);
+        for position in viewport.cells().filter(|p| self.board.is_wall(*p)) {
+            match self.border_style {
+                BorderStyle::Solid => viewport.fill_cell(buffer, position, border),
+                BorderStyle::Dashed => {
+                    let color = if (position.x + position.y).is_multiple_of(2) {
+                        border
+                    } else {
+                        faded
+                    };
+                    viewport.fill_cell(buffer, position, color);
+                }
+                BorderStyle::Checkered => viewport.fill_cell_with(buffer, position, |x, y| {
+                    if (x / half + y / half).is_multiple_of(2) {
+                        border
+                    } else {
+                        faded
+                    }
+                }),
+            }
+        }
+
+        if self.show_danger && !self.game_over && !self.won {
+            self.render_danger(buffer, &viewport);
+        }
+
+        self.render_hud(buffer, &viewport);
+
+        if self.show_legend {
+            self.render_legend(buffer);
+        }
+
+        // Draw victory screen (gold) over the dimmed board
+        if self.attract {
+            self.render_attract(buffer);
+        } else if self.won {
+            effects::draw_overlay(buffer, VICTORY_DIM);
+            let y = WINDOW_HEIGHT / 2 - text::GLYPH_HEIGHT * 8;
+            text::draw_text_centered(buffer, WINDOW_WIDTH, y, "YOU WIN!", 8, 0xFFD700);
+            text::draw_text_centered(
+                buffer,
+                WINDOW_WIDTH,
+                y + text::GLYPH_HEIGHT * 10,
+                &format!("SCORE {}  -  PRESS R TO PLAY AGAIN", self.score),
+                3,
+                0xFFD700,
+            );
+            if self.new_high_score {
+                text::draw_text_centered(
+                    buffer,
+                    WINDOW_WIDTH,
+                    y + text::GLYPH_HEIGHT * 15,
+                    "NEW HIGH SCORE!",
+                    4,
+                    self.celebration_color(),
+                );
+            }
+        } else if self.game_over && self.new_high_score {
+            self.render_new_high_score(buffer);
+        } else if let Some(row) = self.options_row {
+            self.render_options(buffer, row);
+        } else if let Some(selected) = self.pause_menu {
+            self.render_pause_menu(buffer, selected);
+        }
+    }
+
+    // The title over the demo, dimmed less than the menus so the demo shows
+    fn render_attract(&self, buffer: &mut [u32]) {
+        effects::draw_overlay(buffer, ATTRACT_DIM);
+        let y = WINDOW_HEIGHT / 2 - text::GLYPH_HEIGHT * 8;
+        text::draw_text_centered(buffer, WINDOW_WIDTH, y, "RUSTY SNAKE", 8, 0xFFFFFF);
+        text::draw_text_centered(
+            buffer,
+            WINDOW_WIDTH,
+            y + text::GLYPH_HEIGHT * 12,
+            "PRESS ANY KEY TO BEGIN",
+            3,
+            0xFFFFFF,
+        );
+    }
+
+    // The options screen over the dimmed board, laid out like the pause menu
+    // with each row's setting between arrows
+    fn render_options(&self, buffer: &mut [u32], selected: OptionsRow) {
+        effects::draw_overlay(buffer, PAUSE_DIM);
+        let options = self.options();
+        let y = WINDOW_HEIGHT / 2 - text::GLYPH_HEIGHT * 12;
+        text::draw_text_centered(buffer, WINDOW_WIDTH, y, "OPTIONS", 6, 0xFFFFFF);
+        for (i, row) in OptionsRow::ALL.into_iter().enumerate() {
+            let label = match row {
+                OptionsRow::Speed => format!("SPEED < {} MS >", options.refresh_rate),
+                OptionsRow::Theme => format!(
+                    "THEME < {} >",
+                    options
+                        .theme
+                        .preset_name()
+                        .unwrap_or("custom")
+                        .to_ascii_uppercase()
+                ),
+                OptionsRow::Walls => {
+                    format!(
+                        "WALLS < {} >",
+                        if options.wall_grace {
+                            "GRACE"
+                        } else {
+                            "DEADLY"
+                        }
+                    )
+                }
+                OptionsRow::Back => "BACK".to_string(),
+            };
+            let (label, color) = if row == selected {
+                (format!("> {}", label), 0xFFFFFF)
+            } else {
+                (label, PAUSE_INACTIVE)
+            };
+            text::draw_text_centered(
+                buffer,
+                WINDOW_WIDTH,
+                y + text::GLYPH_HEIGHT * (9 + 5 * i),
+                &label,
+                3,
+                color,
+            );
+        }
+        text::draw_text_centered(
+            buffer,
+            WINDOW_WIDTH,
+            y + text::GLYPH_HEIGHT * 30,
+            "CHANGES APPLY FROM THE NEXT GAME",
+            2,
+            PAUSE_INACTIVE,
+        );
+    }
+
+    // The pause menu over the dimmed board, the selected entry in white
+    // behind an arrow and the others in gray
+    fn render_pause_menu(&self, buffer: &mut [u32], selected: PauseChoice) {
+        effects::draw_overlay(buffer, PAUSE_DIM);
+        let y = WINDOW_HEIGHT / 2 - text::GLYPH_HEIGHT * 8;
+        text::draw_text_centered(buffer, WINDOW_WIDTH, y, "PAUSED", 6, 0xFFFFFF);
+        for (i, choice) in PauseChoice::ALL.into_iter().enumerate() {
+            let (label, color) = if choice == selected {
+                (format!("> {}", choice.label()), 0xFFFFFF)
+            } else {
+                (choice.label().to_string(), PAUSE_INACTIVE)
+            };
+            text::draw_text_centered(
+                buffer,
+                WINDOW_WIDTH,
+                y + text::GLYPH_HEIGHT * (9 + 5 * i),
+                &label,
+                3,
+                color,
+            );
+        }
+    }
+
+    // Draw the score on the top border, in the background color so it reads
+    // against the border, or the border color once the camera has scrolled
+    // the top wall off screen. The best score, when tracked, goes at the
+    // right end of the same line. Both shrink to fit narrow boards, and the
+    // best score is left out if they still can't fit side by side.
+    fn render_hud(&self, buffer: &mut [u32], viewport: &Viewport) {
+        let cell_size = viewport.cell_size;
+        let score = match self.win_condition {
+            _ if self.zen => "ZEN".to_string(),
+            Some(WinCondition::TargetScore(target)) => format!("SCORE {}/{}", self.score, target),
+            Some(WinCondition::TargetLength(target)) => {
+                format!(
+                    "SCORE {}  LENGTH {}/{}",
+                    self.score,
+                    self.snake.body.len(),
+                    target
+                )
+            }
+            None => format!("SCORE {}", self.score),
+        };
+        let best = self
+            .high_score
+            .filter(|_| !self.zen)
+            .map(|best| format!("BEST {}", best));
+        let color = if viewport.origin.y == 0 {
+            self.theme.background
+        } else {
+            self.theme.border
+        };
+
+        // Text runs along the top wall between the two corner cells
+        let left = viewport.offset_x + cell_size;
+        let width = (viewport.cols * cell_size).saturating_sub(2 * cell_size);
+        let max_scale = (cell_size / text::GLYPH_HEIGHT).max(1);
+        let fit = best.as_ref().and_then(|best| {
+            (1..=max_scale).rev().find_map(|scale| {
+                let margin = cell_size.saturating_sub(text::GLYPH_HEIGHT * scale) / 2;
+                let gap = (text::GLYPH_HEIGHT + 1) * scale;
+                let needed = text::text_width(&score, scale)
+                    + gap
+                    + text::text_width(best, scale)
+                    + 2 * margin;
+                (needed <= width).then_some((best, scale, margin))
+            })
+        });
+        let (scale, margin) = match fit {
+            Some((_, scale, margin)) => (scale, margin),
+            None => (
+                max_scale,
+                cell_size.saturating_sub(text::GLYPH_HEIGHT * max_scale) / 2,
+            ),
+        };
+        let y = viewport.offset_y + margin;
+
+        text::draw_text(buffer, WINDOW_WIDTH, left + margin, y, &score, scale, color);
+        if let Some((best, _, _)) = fit {
+            let x = (left + width).saturating_sub(margin + text::text_width(best, scale));
+            text::draw_text(buffer, WINDOW_WIDTH, x, y, best, scale, color);
+        }
+    }
+
+    // A game over that beat the best score gets its own screen instead of
+    // just freezing the board
+    fn render_new_high_score(&self, buffer: &mut [u32]) {
+        effects::draw_overlay(buffer, VICTORY_DIM);
+        let y = WINDOW_HEIGHT / 2 - text::GLYPH_HEIGHT * 6;
+        text::draw_text_centered(
+            buffer,
+            WINDOW_WIDTH,
+            y,
+            "NEW HIGH SCORE!",
+            6,
+            self.celebration_color(),
+        );
+        text::draw_text_centered(
+            buffer,
+            WINDOW_WIDTH,
+            y + text::GLYPH_HEIGHT * 8,
+            &format!("SCORE {}  -  PRESS R TO PLAY AGAIN", self.score),
+            3,
+            0xFFD700,
+        );
+    }
+
+    // Alternates between gold and white, timed from the final step
+    fn celebration_color(&self) -> u32 {
+        if (self.last_update.elapsed().as_millis() / CELEBRATION_BLINK_MS).is_multiple_of(2) {
+            0xFFD700
+        } else {
+            0xFFFFFF
+        }
+    }
+
+    // Write `number` in the middle of the food's cell
+    fn render_food_order(
+        &self,
+        buffer: &mut [u32],
+        viewport: &Viewport,
+        position: Position,
+        number: usize,
+    ) {
+        let Some((x, y)) = viewport.screen_position(position) else {
+            return;
+        };
+        let label = number.to_string();
+        let scale = (viewport.cell_size / (text::GLYPH_HEIGHT + 2)).max(1);
+        let width = text::text_width(&label, scale);
+        let height = text::GLYPH_HEIGHT * scale;
+        text::draw_text(
+            buffer,
+            WINDOW_WIDTH,
+            (x + viewport.cell_size / 2).saturating_sub(width / 2),
+            (y + viewport.cell_size / 2).saturating_sub(height / 2),
+            &label,
+            scale,
+            self.theme.background,
+        );
+    }
+
+    // Draw the food's current value just above it, or below it when the
+    // food sits in the top row of the screen
+    fn render_food_value(&self, buffer: &mut [u32], viewport: &Viewport, food: &Food) {
+        let Some((x, y)) = viewport.screen_position(food.position) else {
+            return;
+        };
+        let label = self.food_points(food).to_string();
+        let scale = (viewport.cell_size / text::GLYPH_HEIGHT).max(1);
+        let height = text::GLYPH_HEIGHT * scale;
+        let gap = scale;
+        let label_x =
+            (x + viewport.cell_size / 2).saturating_sub(text::text_width(&label, scale) / 2);
+        let label_y = if y >= height + gap {
+            y - height - gap
+        } else {
+            y + viewport.cell_size + gap
+        };
+        text::draw_text(
+            buffer,
+            WINDOW_WIDTH,
+            label_x,
+            label_y,
+            &label,
+            scale,
+            self.food_color(food.kind),
+        );
+    }
+
+    fn food_color(&self, kind: FoodKind) -> u32 {
+        match kind {
+            FoodKind::Normal => self.theme.food,
+            FoodKind::Ghost => effects::blend(self.theme.food, 0xFFFFFF, GHOST_FADE),
+            FoodKind::Split => effects::blend(self.theme.food, self.theme.snake, 0.5),
+        }
+    }
+
+    // What each color on the board means, for the elements this game uses
+    fn legend_entries(&self) -> Vec<(u32, &'static str)> {
+        let wall = if self.invincible {
+            "WALL - BLOCKS YOU"
+        } else {
+            "WALL - DEADLY"
+        };
+        let mut entries = vec![(self.theme.snake, "SNAKE - YOU")];
+        if !self.zen {
+            entries.push((self.theme.food, "FOOD - EAT TO GROW"));
+            if self.ordered_food {
+                let later = effects::blend(self.theme.food, self.theme.background, ORDER_FADE);
+                entries.push((later, "LATER FOOD - EAT IN ORDER"));
+            }
+            if self.food_bag.contains(FoodKind::Ghost) {
+                entries.push((self.food_color(FoodKind::Ghost), "GHOST - PASS THROUGH YOU"));
+            }
+            if self.food_bag.contains(FoodKind::Split) {
+                entries.push((self.food_color(FoodKind::Split), "SPLIT - HALVES YOU"));
+            }
+        }
+        if self.zone.is_some() {
+            let tint = effects::blend(self.theme.background, self.theme.border, ZONE_TINT);
+            entries.push((tint, "ZONE - BONUS POINTS"));
+        }
+        entries.push((self.theme.border, wall));
+        entries
+    }
+
+    // A panel in the top-right corner with a color swatch and label per entry
+    fn render_legend(&self, buffer: &mut [u32]) {
+        let entries = self.legend_entries();
+        let line_height = (text::GLYPH_HEIGHT + 4) * LEGEND_SCALE;
+        let swatch = text::GLYPH_HEIGHT * LEGEND_SCALE;
+        let label_width = entries
+            .iter()
+            .map(|(_, label)| text::text_width(label, LEGEND_SCALE))
+            .max()
+            .unwrap_or(0);
+        let width = LEGEND_PADDING * 3 + swatch + label_width;
+        let height = LEGEND_PADDING * 2 + line_height * entries.len();
+        let left = WINDOW_WIDTH.saturating_sub(width + GRID_SIZE * 2);
+        let top = GRID_SIZE * 2;
+
+        for y in top..(top + height).min(WINDOW_HEIGHT) {
+            for x in left..(left + width).min(WINDOW_WIDTH) {
+                let edge = x == left || x == left + width - 1 || y == top || y == top + height - 1;
+                buffer[y * WINDOW_WIDTH + x] = if edge {
+                    self.theme.border
+                } else {
+                    self.theme.background
+                };
+            }
+        }
+
+        for (i, (color, label)) in entries.iter().enumerate() {
+            let y = top + LEGEND_PADDING + i * line_height;
+            for py in y..(y + swatch).min(WINDOW_HEIGHT) {
+                for px in left + LEGEND_PADDING..(left + LEGEND_PADDING + swatch).min(WINDOW_WIDTH)
+                {
+                    buffer[py * WINDOW_WIDTH + px] = *color;
+                }
+            }
+            text::draw_text(
+                buffer,
+                WINDOW_WIDTH,
+                left + LEGEND_PADDING * 2 + swatch,
+                y,
+                label,
+                LEGEND_SCALE,
+                self.theme.border,
+            );
+        }
+    }
+
+    fn restart(&mut self) {
+        self.apply_pending_options();
+        self.tail_grew = false;
+        self.board = self.start_board.clone();
+        self.snake = self.start_snake.clone();
+        // Counters go back to zero before anything is placed, since new food
+        // is stamped with the current tick
+        self.score = self.start_score;
+        self.new_high_score = false;
+        self.ghost_ticks = 0;
+        self.holding_at_wall = false;
+        self.steered = false;
+        self.ended_at = None;
+        self.particles.clear();
+        self.assisted = self.autopilot.is_some() || self.attract;
+        self.game_over = false;
+        self.won = false;
+        self.last_update = Instant::now();
+        self.ticks = 0;
+        self.input_log.clear();
+        if self.restart_seed == RestartSeed::New {
+            self.seed = rand::rng().random();
+        }
+        self.rng = ChaCha8Rng::seed_from_u64(self.seed);
+        self.autopilot_rng = ChaCha8Rng::seed_from_u64(self.seed);
+        self.food_bag.empty();
+        self.food_bag_rng = ChaCha8Rng::seed_from_u64(self.seed);
+        if self.random_colors {
+            self.theme = seeded_theme(self.seed);
+            self.paint_backdrop();
+        }
+        self.food.clear();
+        self.regen_timer = 0;
+        self.sequence_index = 0;
+        self.place_starting_food();
+        self.zone_rng = ChaCha8Rng::seed_from_u64(self.seed);
+        self.move_zone();
+    }
+}
+
+// 64-bit FNV-1a, with every integer fed in as eight little-endian bytes, so
+// the hash doesn't depend on the platform's word size or byte order the way
+// the standard library's hasher may
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x100_0000_01b3);
+        }
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.write_u64(n.into());
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write_u64(n.into());
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_isize(&mut self, n: isize) {
+        self.write_u64(n as u64);
+    }
+}
+
+// Palettes come from their own generator seeded like the game's, so turning
+// random colors on doesn't change where food appears for a given seed
+fn seeded_theme(seed: u64) -> Theme {
+    Theme::random(&mut ChaCha8Rng::seed_from_u64(seed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refresh_rate_is_clamped_to_the_minimum() {
+        for refresh_rate in [0, 1, MIN_REFRESH_RATE - 1] {
+            let game = Game::new(GameConfig {
+                refresh_rate,
+                ..GameConfig::default()
+            });
+            assert_eq!(game.refresh_rate, Duration::from_millis(MIN_REFRESH_RATE));
+            assert_eq!(
+                game.vertical_refresh_rate,
+                Duration::from_millis(MIN_REFRESH_RATE)
+            );
+        }
+    }
+
+    #[test]
+    fn refresh_rate_at_or_above_the_minimum_is_kept() {
+        for refresh_rate in [MIN_REFRESH_RATE, 150, SLOW_REFRESH_RATE * 2] {
+            let game = Game::new(GameConfig {
+                refresh_rate,
+                ..GameConfig::default()
+            });
+            assert_eq!(game.refresh_rate, Duration::from_millis(refresh_rate));
+        }
+    }
+
+    // Every interior cell of `board` except `free`
+    fn body_filling(board: &Board, free: &[Position]) -> Vec<Position> {
+        (1..board.height - 1)
+            .flat_map(|y| (1..board.width - 1).map(move |x| Position { x, y }))
+            .filter(|cell| !free.contains(cell))
+            .collect()
+    }
+
+    #[test]
+    fn spawn_finds_the_last_free_cell_on_a_small_board() {
+        let board = Board::new(6, 6);
+        let free = Position { x: 4, y: 4 };
+        let snake = Snake::init(body_filling(&board, &[free])).unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut food = Food::new();
+        assert!(food.spawn_with_rng(&snake, &[], &board, SpawnStrategy::Uniform, &mut rng));
+        assert_eq!(food.position, free);
+    }
+
+    #[test]
+    fn early_game_spawn_falls_back_when_guesses_miss() {
+        let board = Board::new(6, 6);
+        let free = Position { x: 1, y: 3 };
+        let snake = Snake::init(body_filling(&board, &[free])).unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut food = Food::new();
+        assert!(food.spawn_early_game(&snake, &[], &board, &mut rng));
+        assert_eq!(food.position, free);
+    }
+
+    #[test]
+    fn spawn_fails_without_a_free_cell() {
+        let board = Board::new(6, 6);
+        let snake = Snake::init(body_filling(&board, &[])).unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut food = Food::new();
+        assert!(!food.spawn_with_rng(&snake, &[], &board, SpawnStrategy::Uniform, &mut rng));
+        assert!(!food.spawn_early_game(&snake, &[], &board, &mut rng));
+    }
+
+    #[test]
+    fn autopilot_game_on_a_small_board_ends() {
+        let mut game = Game::new(GameConfig {
+            board: Board::new(6, 6),
+            autopilot: Some(AutopilotStrategy::Safe),
+            seed: Some(0),
+            ..GameConfig::default()
+        });
+        assert!(game.simulate_bounded(&[], 10_000));
+    }
+
+    #[test]
+    fn center_biased_spawns_land_further_from_the_walls() {
+        let board = Board::default();
+        let snake = Snake::init(vec![Position { x: 1, y: 1 }]).unwrap();
+        let wall_distance = |position: Position| {
+            position
+                .x
+                .min(board.width - 1 - position.x)
+                .min(position.y)
+                .min(board.height - 1 - position.y)
+        };
+        let mean_distance = |strategy: SpawnStrategy| {
+            let mut rng = ChaCha8Rng::seed_from_u64(0);
+            let mut food = Food::new();
+            let spawns = 2_000;
+            let total: usize = (0..spawns)
+                .map(|_| {
+                    assert!(food.spawn_with_rng(&snake, &[], &board, strategy, &mut rng));
+                    wall_distance(food.position)
+                })
+                .sum();
+            total as f64 / spawns as f64
+        };
+        let uniform = mean_distance(SpawnStrategy::Uniform);
+        let biased = mean_distance(SpawnStrategy::CenterBiased);
+        assert!(biased > uniform * 1.2, "{biased} vs {uniform}");
+    }
+
+    // A five-cell snake heading Right that, turned Up, Left and then Down,
+    // puts its head back on its own body on the third step
+    fn game_looping_into_itself(ghost_ticks: u64) -> Game {
+        let body = (6..=10).rev().map(|x| Position { x, y: 10 }).collect();
+        let snake = Snake::init(body).unwrap();
+        let mut game = Game::new(
+            GameConfig::default()
+                .with_snake(snake)
+                .with_food_sequence(vec![Position { x: 30, y: 30 }]),
+        );
+        game.ghost_ticks = ghost_ticks;
+        game
+    }
+
+    fn loop_into_itself(game: &mut Game) {
+        for direction in [Direction::Up, Direction::Left, Direction::Down] {
+            game.turn(direction);
+            game.step();
+        }
+    }
+
+    #[test]
+    fn ghost_snake_passes_through_itself_while_ticks_remain() {
+        let mut game = game_looping_into_itself(3);
+        loop_into_itself(&mut game);
+        assert!(!game.is_game_over());
+        assert_eq!(game.snake().body()[0], Position { x: 9, y: 10 });
+        assert_eq!(game.ghost_ticks(), 0);
+    }
+
+    #[test]
+    fn ghost_snake_collides_once_ticks_run_out() {
+        let mut game = game_looping_into_itself(2);
+        loop_into_itself(&mut game);
+        assert!(game.is_game_over());
+    }
+
+    #[test]
+    fn ghost_snake_still_hits_walls() {
+        let snake = Snake::init(vec![Position { x: 10, y: 1 }]).unwrap();
+        let mut game = Game::new(GameConfig::default().with_snake(snake));
+        game.ghost_ticks = 10;
+        game.turn(Direction::Up);
+        game.step();
+        assert!(game.is_game_over());
+    }
+
+    #[test]
+    fn eating_ghost_food_starts_the_countdown() {
+        let start = Board::default().center();
+        let mut game = Game::new(
+            GameConfig {
+                food_mix: vec![(FoodKind::Ghost, 1)],
+                ghost_ticks: 5,
+                ..GameConfig::default()
+            }
+            .with_food_sequence(vec![Position {
+                x: start.x + 1,
+                y: start.y,
+            }]),
+        );
+        game.step();
+        assert_eq!(game.ghost_ticks(), 5);
+        game.step();
+        assert_eq!(game.ghost_ticks(), 4);
+    }
+
+    #[test]
+    fn food_on_the_board_never_exceeds_the_cap() {
+        for food_regen_ticks in [0, 1, 4] {
+            let mut game = Game::new(GameConfig {
+                board: Board::new(12, 12),
+                autopilot: Some(AutopilotStrategy::Safe),
+                seed: Some(3),
+                max_food: 3,
+                food_regen_ticks,
+                ..GameConfig::default()
+            });
+            assert_eq!(game.food_positions().len(), 3);
+            let mut eaten = 0;
+            for _ in 0..500 {
+                if game.is_game_over() || game.is_won() {
+                    break;
+                }
+                let events = game.step();
+                eaten += events
+                    .iter()
+                    .filter(|timed| matches!(timed.event, GameEvent::Ate(_)))
+                    .count();
+                assert!(game.food_positions().len() <= 3);
+            }
+            assert!(
+                eaten > 3,
+                "only {eaten} eaten with regen {food_regen_ticks}"
+            );
+        }
+    }
+
+    #[test]
+    fn eaten_food_regrows_after_the_regen_delay() {
+        let start = Board::default().center();
+        let mut game = Game::new(
+            GameConfig {
+                max_food: 2,
+                food_regen_ticks: 3,
+                ..GameConfig::default()
+            }
+            .with_food_sequence(vec![
+                Position {
+                    x: start.x + 1,
+                    y: start.y,
+                },
+                Position {
+                    x: start.x,
+                    y: start.y + 5,
+                },
+            ]),
+        );
+        // The tick the food is eaten on counts toward the delay
+        game.step();
+        assert_eq!(game.food_positions().len(), 1);
+        game.step();
+        assert_eq!(game.food_positions().len(), 1);
+        game.step();
+        assert_eq!(game.food_positions().len(), 2);
+    }
+
+    #[test]
+    fn validate_rejects_a_cap_that_leaves_no_room() {
+        let board = Board::new(6, 6);
+        let config = |max_food| GameConfig {
+            board: board.clone(),
+            max_food,
+            ..GameConfig::default()
+        };
+        assert!(config(0).validate().is_err());
+        assert!(config(board.playable_cells()).validate().is_err());
+        assert!(config(board.playable_cells() - 1).validate().is_ok());
+    }
+
+    #[test]
+    fn same_seed_restart_reproduces_the_first_food() {
+        let mut game = Game::new(GameConfig {
+            seed: Some(7),
+            restart_seed: RestartSeed::Same,
+            ..GameConfig::default()
+        });
+        let first_food = game.food_positions();
+        for _ in 0..5 {
+            game.step();
+        }
+        game.restart();
+        assert_eq!(game.seed(), 7);
+        assert_eq!(game.food_positions(), first_food);
+    }
+
+    #[test]
+    fn new_seed_restart_draws_another_seed() {
+        let mut game = Game::new(GameConfig {
+            seed: Some(7),
+            restart_seed: RestartSeed::New,
+            ..GameConfig::default()
+        });
+        game.restart();
+        assert_ne!(game.seed(), 7);
+    }
+
+    #[test]
+    fn pressing_the_current_heading_takes_back_a_queued_turn() {
+        let mut game = Game::new(GameConfig {
+            seed: Some(0),
+            ..GameConfig::default()
+        });
+        let head = game.snake().body()[0];
+        assert!(game.turn(Direction::Up));
+        assert!(game.turn(Direction::Right));
+        assert_eq!(game.snake().queued_direction(), None);
+        game.step();
+        assert_eq!(game.snake().direction(), Direction::Right);
+        assert_eq!(
+            game.snake().body()[0],
+            Position {
+                x: head.x + 1,
+                y: head.y
+            }
+        );
+        assert_eq!(
+            game.input_log(),
+            &[(0, Direction::Up), (0, Direction::Right)]
+        );
+    }
+
+    #[test]
+    fn pressing_the_current_heading_with_nothing_queued_is_ignored() {
+        let mut game = Game::default();
+        assert!(!game.turn(Direction::Right));
+        assert!(game.input_log().is_empty());
+    }
+
+    #[test]
+    fn food_value_decays_to_its_floor() {
+        let value = FoodValue {
+            base: 100,
+            decay: 3,
+            floor: 10,
+        };
+        assert_eq!(value.points(0), 100);
+        assert_eq!(value.points(1), 97);
+        assert_eq!(value.points(10), 70);
+        assert_eq!(value.points(30), 10);
+        assert_eq!(value.points(31), 10);
+        assert_eq!(value.points(u64::MAX), 10);
+    }
+
+    #[test]
+    fn food_value_without_decay_stays_at_its_base() {
+        let value = FoodValue::default();
+        assert_eq!(value.points(0), value.base);
+        assert_eq!(value.points(1_000), value.base);
+    }
+
+    #[test]
+    fn food_eaten_after_restart_scores_from_the_new_game() {
+        let start = Board::default().center();
+        let food = Position {
+            x: start.x + 3,
+            y: start.y,
+        };
+        let mut game = Game::new(
+            GameConfig {
+                seed: Some(0),
+                food_value: FoodValue {
+                    base: 100,
+                    decay: 1,
+                    floor: 5,
+                },
+                ..GameConfig::default()
+            }
+            .with_food_sequence(vec![food]),
+        );
+        for _ in 0..25 {
+            game.step();
+        }
+        assert!(!game.is_game_over());
+        game.pause();
+        game.choose(PauseChoice::Restart);
+        assert_eq!(game.ticks(), 0);
+
+        for _ in 0..3 {
+            game.step();
+        }
+        assert_eq!(game.score(), 97);
+    }
+
+    #[test]
+    fn game_starts_and_restarts_with_the_configured_snake() {
+        let body = vec![
+            Position { x: 5, y: 10 },
+            Position { x: 5, y: 11 },
+            Position { x: 5, y: 12 },
+        ];
+        let snake = Snake::with(body.clone(), Direction::Up, 2).unwrap();
+        let mut game = Game::new(GameConfig::default().with_snake(snake));
+        assert_eq!(
+            game.snake().body().iter().copied().collect::<Vec<_>>(),
+            body
+        );
+        assert_eq!(game.snake().direction(), Direction::Up);
+
+        game.step();
+        assert_eq!(game.snake().body()[0], Position { x: 5, y: 9 });
+        assert_eq!(game.snake().body().len(), 4);
+
+        game.restart();
+        assert_eq!(
+            game.snake().body().iter().copied().collect::<Vec<_>>(),
+            body
+        );
+        assert_eq!(game.snake().pending_growth(), 2);
+    }
+
+    #[test]
+    fn validate_rejects_a_snake_on_the_wall() {
+        let snake = Snake::init(vec![Position { x: 1, y: 1 }, Position { x: 0, y: 1 }]).unwrap();
+        let config = GameConfig::default().with_snake(snake);
+        assert!(matches!(config.validate(), Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn validate_rejects_a_snake_with_a_start_position() {
+        let snake = Snake::init(vec![Position { x: 3, y: 3 }]).unwrap();
+        let config = GameConfig {
+            start: Some(Position { x: 5, y: 5 }),
+            ..GameConfig::default().with_snake(snake)
+        };
+        assert!(matches!(config.validate(), Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn snake_with_an_empty_body_is_rejected() {
+        assert!(Snake::with(Vec::new(), Direction::Left, 0).is_none());
+    }
+
+    // A game whose snake heads `direction` from the middle of the board with
+    // `length` cells trailing straight behind it
+    fn game_heading(direction: Direction, length: usize, growth: usize, rule: ReverseRule) -> Game {
+        let mut body = vec![Board::default().center()];
+        for _ in 1..length {
+            body.push(body[body.len() - 1].neighbor(direction.opposite()));
+        }
+        let snake = Snake::with(body, direction, growth).unwrap();
+        Game::new(GameConfig {
+            seed: Some(0),
+            reverse_rule: rule,
+            ..GameConfig::default().with_snake(snake)
+        })
+    }
+
+    #[test]
+    fn reversing_a_one_cell_snake_turns_it_around() {
+        for direction in Direction::ALL {
+            let mut game = game_heading(direction, 1, 0, ReverseRule::Collide);
+            let head = game.snake().body()[0];
+            assert!(game.turn(direction.opposite()));
+            game.step();
+            assert!(!game.is_game_over(), "{direction}");
+            assert_eq!(game.snake().body()[0], head.neighbor(direction.opposite()));
+        }
+    }
+
+    #[test]
+    fn reversing_a_two_cell_snake_swaps_head_and_tail() {
+        for direction in Direction::ALL {
+            let mut game = game_heading(direction, 2, 0, ReverseRule::Collide);
+            let body = game.snake().body().clone();
+            assert!(game.turn(direction.opposite()));
+            game.step();
+            assert!(!game.is_game_over(), "{direction}");
+            assert_eq!(game.snake().body()[0], body[1]);
+            assert_eq!(game.snake().body()[1], body[0]);
+        }
+    }
+
+    #[test]
+    fn reversing_a_growing_two_cell_snake_hits_the_neck() {
+        for direction in Direction::ALL {
+            let mut game = game_heading(direction, 2, 1, ReverseRule::Collide);
+            assert_eq!(
+                game.would_collide(direction.opposite()),
+                Some(CollisionKind::Body)
+            );
+            assert!(game.turn(direction.opposite()));
+            game.step();
+            assert!(game.is_game_over(), "{direction}");
+        }
+    }
+
+    #[test]
+    fn reversing_a_longer_snake_hits_the_neck() {
+        for length in 3..=5 {
+            for direction in Direction::ALL {
+                let mut game = game_heading(direction, length, 0, ReverseRule::Collide);
+                assert!(game.turn(direction.opposite()));
+                game.step();
+                assert!(game.is_game_over(), "{direction} at length {length}");
+            }
+        }
+    }
+
+    #[test]
+    fn ignored_reverses_never_reach_the_neck() {
+        for length in 1..=4 {
+            for direction in Direction::ALL {
+                let mut game = game_heading(direction, length, 0, ReverseRule::Ignore);
+                assert!(!game.turn(direction.opposite()));
+                game.step();
+                assert!(!game.is_game_over(), "{direction} at length {length}");
+                assert_eq!(game.snake().direction(), direction);
+            }
+        }
+    }
+
+    #[test]
+    fn validate_rejects_boards_below_the_minimum_size() {
+        for (width, height) in [(0, 0), (1, 10), (10, MIN_BOARD_SIZE - 1)] {
+            let config = GameConfig {
+                board: Board::new(width, height),
+                ..GameConfig::default()
+            };
+            assert!(matches!(config.validate(), Err(Error::Validation(_))));
+        }
+        let config = GameConfig {
+            board: Board::new(MIN_BOARD_SIZE, MIN_BOARD_SIZE),
+            ..GameConfig::default()
+        };
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_bindings_on_fixed_keys() {
+        for key in [Key::Enter, Key::T, Key::Minus] {
+            let config = GameConfig {
+                key_bindings: KeyBindings {
+                    pause: key,
+                    ..KeyBindings::default()
+                },
+                ..GameConfig::default()
+            };
+            assert!(matches!(config.validate(), Err(Error::Validation(_))));
+        }
+    }
+
+    #[test]
+    fn validate_rejects_a_key_bound_twice() {
+        let config = GameConfig {
+            key_bindings: KeyBindings {
+                menu: Key::R,
+                ..KeyBindings::default()
+            },
+            ..GameConfig::default()
+        };
+        assert!(matches!(config.validate(), Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn return_to_menu_starts_the_demo_over() {
+        let mut game = Game::new(GameConfig::default());
+        for _ in 0..3 {
+            game.step();
+        }
+        game.pause();
+        game.return_to_menu();
+        assert!(game.is_attract());
+        assert!(!game.is_paused());
+        assert_eq!(game.ticks, 0);
+        assert!(game.assisted);
+    }
+
+    #[test]
+    fn survival_points_accrue_every_tick() {
+        let mut game = Game::new(GameConfig {
+            survival_points: 3,
+            initial_food: vec![Position { x: 1, y: 1 }],
+            ..GameConfig::default()
+        });
+        for _ in 0..5 {
+            game.step();
+        }
+        assert!(!game.is_game_over());
+        assert_eq!(game.score(), 15);
+    }
+
+    #[test]
+    fn food_sequence_scripts_every_meal() {
+        let at = |x, y| Position { x, y };
+        let mut game = Game::new(
+            GameConfig {
+                start: Some(at(5, 5)),
+                ..GameConfig::default()
+            }
+            .with_food_sequence(vec![at(7, 5), at(9, 5), at(9, 8)]),
+        );
+        let food = |game: &Game| {
+            game.food_positions()
+                .iter()
+                .map(|(p, _)| *p)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(food(&game), [at(7, 5)]);
+        game.step();
+        game.step();
+        assert_eq!(food(&game), [at(9, 5)]);
+        game.step();
+        game.step();
+        assert_eq!(food(&game), [at(9, 8)]);
+        game.turn(Direction::Down);
+        for _ in 0..3 {
+            game.step();
+        }
+        assert_eq!(game.snake().body()[0], at(9, 8));
+        assert_eq!(game.score(), 30);
+        assert_eq!(game.snake().body().len() + game.snake().pending_growth(), 4);
+    }
+
+    #[test]
+    fn max_food_lands_on_distinct_free_cells() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        for _ in 0..20 {
+            let game = Game::new(GameConfig {
+                board: Board::new(8, 8),
+                max_food: 30,
+                seed: Some(rng.random()),
+                ..GameConfig::default()
+            });
+            let food: Vec<Position> = game.food_positions().iter().map(|(p, _)| *p).collect();
+            assert_eq!(food.len(), 30);
+            for (i, position) in food.iter().enumerate() {
+                assert!(!food[i + 1..].contains(position));
+                assert!(!game.snake().body().contains(position));
+                assert!(!game.board.is_wall(*position));
+            }
+        }
+    }
+
+    #[test]
+    fn auto_restart_waits_out_the_delay() {
+        let mut game = Game::new(GameConfig {
+            auto_restart: Some(Duration::from_secs(5)),
+            ..GameConfig::default()
+        });
+        game.game_over = true;
+        let ended = Instant::now();
+        game.update_at(ended, &mut NoTelemetry);
+        game.update_at(ended + Duration::from_millis(4999), &mut NoTelemetry);
+        assert!(game.is_game_over());
+        game.update_at(ended + Duration::from_secs(5), &mut NoTelemetry);
+        assert!(!game.is_game_over());
+        assert_eq!(game.ended_at, None);
+    }
+
+    #[test]
+    fn award_saturates_instead_of_wrapping() {
+        let mut game = Game::new(GameConfig {
+            start_score: 5,
+            ..GameConfig::default()
+        });
+        game.award(-20);
+        assert_eq!(game.score(), 0);
+        game.score = u32::MAX - 1;
+        game.award(10);
+        assert_eq!(game.score(), u32::MAX);
+    }
+
+    // Pinned values: if these change, a rand or rand_chacha upgrade has
+    // changed the stream, and saved seeds no longer replay the same games
+    #[test]
+    fn seed_gives_the_same_board_on_every_run() {
+        let game = Game::new(GameConfig {
+            seed: Some(42),
+            max_food: 4,
+            ..GameConfig::default()
+        });
+        let at = |x, y| (Position { x, y }, FoodKind::Normal);
+        assert_eq!(
+            game.food_positions(),
+            [at(14, 24), at(10, 33), at(48, 15), at(22, 22)]
+        );
+        assert_eq!(game.state_hash(), 0x9024_e8c3_f5c4_56ef);
+    }
+
+    #[test]
+    fn validate_rejects_a_start_off_the_floor() {
+        let board = Board::with_walls(10, 10, &[Position { x: 4, y: 4 }]);
+        for (x, y) in [(10, 5), (5, 10), (0, 5), (4, 4)] {
+            let config = GameConfig {
+                board: board.clone(),
+                start: Some(Position { x, y }),
+                ..GameConfig::default()
+            };
+            assert!(matches!(config.validate(), Err(Error::Validation(_))));
+        }
+        let config = GameConfig {
+            board,
+            start: Some(Position { x: 5, y: 5 }),
+            ..GameConfig::default()
+        };
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn viewport_letterboxes_cells_that_dont_divide_the_window() {
+        let big = Board::new(500, 300);
+        let viewports = [
+            Viewport::new(&Board::new(33, 21), Position { x: 0, y: 0 }, 0),
+            Viewport::new(&Board::new(37, 23), Position { x: 0, y: 0 }, 0),
+            Viewport::new(&big, big.center(), 3),
+            Viewport::new(&big, Position { x: 499, y: 299 }, 5),
+        ];
+        for viewport in viewports {
+            let drawn_width = viewport.cols * viewport.cell_size;
+            let drawn_height = viewport.rows * viewport.cell_size;
+            assert!(drawn_width <= WINDOW_WIDTH && drawn_height <= WINDOW_HEIGHT);
+            let right = WINDOW_WIDTH - drawn_width - viewport.offset_x;
+            let bottom = WINDOW_HEIGHT - drawn_height - viewport.offset_y;
+            assert!(viewport.offset_x.abs_diff(right) <= 1);
+            assert!(viewport.offset_y.abs_diff(bottom) <= 1);
+            // Every case leaves a margin to center
+            assert!(drawn_width < WINDOW_WIDTH || drawn_height < WINDOW_HEIGHT);
+        }
+    }
+
+    #[test]
+    fn warmup_growth_stretches_the_snake_out() {
+        let warmup = |zen| {
+            Game::new(GameConfig {
+                warmup_growth: 4,
+                initial_food: vec![Position { x: 1, y: 1 }],
+                zen,
+                ..GameConfig::default()
+            })
+        };
+        let mut game = warmup(false);
+        assert_eq!(game.snake().body().len(), 1);
+        for _ in 0..4 {
+            game.step();
+        }
+        assert_eq!(game.snake().body().len(), 5);
+        game.step();
+        game.step();
+        assert_eq!(game.snake().body().len(), 5);
+
+        let mut zen = warmup(true);
+        for _ in 0..4 {
+            zen.step();
+        }
+        assert_eq!(zen.snake().body().len(), 1);
+    }
+
+    // A one-cell snake at `head` heading `direction`, with wall grace on
+    fn game_with_wall_grace(head: Position, direction: Direction) -> Game {
+        let snake = Snake::with(vec![head], direction, 0).unwrap();
+        Game::new(GameConfig {
+            wall_grace: true,
+            initial_food: vec![Position { x: 8, y: 8 }],
+            ..GameConfig::default().with_snake(snake)
+        })
+    }
+
+    #[test]
+    fn wall_grace_holds_a_straight_approach_for_one_tick() {
+        let head = Position { x: 1, y: 5 };
+        let mut game = game_with_wall_grace(head, Direction::Left);
+        game.step();
+        assert!(!game.is_game_over());
+        assert_eq!(game.snake().body()[0], head);
+        game.step();
+        assert!(game.is_game_over());
+
+        let mut game = game_with_wall_grace(head, Direction::Left);
+        game.step();
+        game.turn(Direction::Up);
+        game.step();
+        assert!(!game.is_game_over());
+        assert_eq!(game.snake().body()[0], Position { x: 1, y: 4 });
+    }
+
+    #[test]
+    fn wall_grace_holds_a_corner_approach_for_one_tick() {
+        let corner = Position { x: 1, y: 1 };
+        let mut game = game_with_wall_grace(corner, Direction::Left);
+        game.step();
+        assert!(!game.is_game_over());
+        assert_eq!(game.snake().body()[0], corner);
+        // Turning into the other wall uses up the grace all the same
+        game.turn(Direction::Up);
+        game.step();
+        assert!(game.is_game_over());
+
+        let mut game = game_with_wall_grace(corner, Direction::Left);
+        game.step();
+        game.turn(Direction::Down);
+        game.step();
+        assert!(!game.is_game_over());
+        assert_eq!(game.snake().body()[0], Position { x: 1, y: 2 });
+    }
+
+    #[test]
+    fn entering_the_zone_pays_the_bonus_once() {
+        let mut game = Game::new(GameConfig {
+            start: Some(Position { x: 5, y: 5 }),
+            initial_food: vec![Position { x: 1, y: 1 }],
+            scoring_zone: Some(ScoringZone {
+                size: 2,
+                shift_ticks: 1000,
+                bonus: 25,
+            }),
+            ..GameConfig::default()
+        });
+        game.zone = Some(Zone {
+            origin: Position { x: 7, y: 5 },
+            size: 2,
+        });
+        game.in_zone = false;
+        let entered = |events: Vec<TimedEvent>| {
+            events
+                .iter()
+                .any(|timed| timed.event == GameEvent::EnteredZone)
+        };
+        assert!(!entered(game.step()));
+        assert_eq!(game.score(), 0);
+        assert!(entered(game.step()));
+        assert_eq!(game.score(), 25);
+        // Staying inside, then leaving, pays nothing more
+        assert!(!entered(game.step()));
+        assert!(!entered(game.step()));
+        assert_eq!(game.score(), 25);
+    }
+
+    #[test]
+    fn split_keeps_the_head_half_rounding_up() {
+        for (length, kept) in [(1, 1), (2, 1), (5, 3), (6, 3)] {
+            let body: Vec<Position> = (0..length).map(|i| Position { x: 20 - i, y: 5 }).collect();
+            let mut snake = Snake::init(body.clone()).unwrap();
+            snake.split();
+            assert_eq!(
+                snake.body().iter().copied().collect::<Vec<_>>(),
+                body[..kept]
+            );
+        }
+    }
+
+    #[test]
+    fn split_food_halves_the_snake() {
+        let mut game = game_heading(Direction::Right, 6, 0, ReverseRule::Ignore);
+        let head = game.snake().body()[0];
+        game.food = vec![Food {
+            position: head.neighbor(Direction::Right),
+            kind: FoodKind::Split,
+            spawned_tick: 0,
+        }];
+        game.step();
+        assert_eq!(game.snake().body().len(), 3);
+        assert_eq!(game.snake().pending_growth(), 0);
+        assert_eq!(game.score(), 10);
+    }
+
+    #[test]
+    fn would_collide_checks_each_direction_at_a_wall() {
+        use CollisionKind::{Body, Wall};
+        let cases = [
+            // Along the left wall
+            (5, [None, None, Some(Wall), Some(Body)]),
+            // In the top-left corner
+            (1, [Some(Wall), None, Some(Wall), Some(Body)]),
+        ];
+        for (y, expected) in cases {
+            let body = vec![
+                Position { x: 1, y },
+                Position { x: 2, y },
+                Position { x: 3, y },
+            ];
+            let snake = Snake::with(body, Direction::Left, 0).unwrap();
+            let game = Game::new(GameConfig::default().with_snake(snake));
+            let before = game.state_hash();
+            // Direction::ALL is Up, Down, Left, Right
+            for (direction, expected) in Direction::ALL.into_iter().zip(expected) {
+                assert_eq!(
+                    game.would_collide(direction),
+                    expected,
+                    "{direction:?} at y {y}"
+                );
+            }
+            assert_eq!(game.state_hash(), before);
+        }
+    }
+
+    // An ordered-food game with the snake at (5, 5) heading Right and the
+    // numbered pellets on `food`, oldest first
+    fn game_with_ordered_food(food: Vec<Position>, invincible: bool) -> Game {
+        Game::new(
+            GameConfig {
+                start: Some(Position { x: 5, y: 5 }),
+                max_food: 2,
+                ordered_food: true,
+                initial_food: food,
+                invincible,
+                ..GameConfig::default()
+            }
+            .with_food_sequence(vec![Position { x: 1, y: 1 }, Position { x: 1, y: 2 }]),
+        )
+    }
+
+    #[test]
+    fn ordered_food_eaten_in_order_scores_and_grows() {
+        let mut game = game_with_ordered_food(
+            vec![Position { x: 6, y: 5 }, Position { x: 7, y: 5 }],
+            false,
+        );
+        game.step();
+        assert_eq!(game.score(), 10);
+        game.step();
+        assert!(!game.is_game_over());
+        assert_eq!(game.score(), 20);
+        assert_eq!(game.snake().body().len() + game.snake().pending_growth(), 3);
+    }
+
+    #[test]
+    fn ordered_food_eaten_out_of_order_ends_the_run() {
+        let out_of_order = vec![Position { x: 7, y: 5 }, Position { x: 6, y: 5 }];
+        let mut game = game_with_ordered_food(out_of_order.clone(), false);
+        let events = game.step();
+        assert!(game.is_game_over());
+        assert!(events.iter().any(|timed| timed.event == GameEvent::Died));
+        assert_eq!(game.score(), 0);
+
+        // An invincible snake passes over it instead, then eats in order
+        let mut game = game_with_ordered_food(out_of_order, true);
+        game.step();
+        assert!(!game.is_game_over());
+        assert_eq!(game.score(), 0);
+        assert_eq!(game.food_positions().len(), 2);
+        game.step();
+        assert_eq!(game.score(), 10);
+    }
+
+    #[test]
+    fn fill_fraction_at_known_lengths() {
+        // A 6x6 board has a 4x4 floor, walked row by row, back and forth
+        let board = Board::new(6, 6);
+        assert_eq!(board.playable_cells(), 16);
+        let path: Vec<Position> = (1..5)
+            .flat_map(|y| {
+                let row: Vec<usize> = if y % 2 == 1 {
+                    vec![1, 2, 3, 4]
+                } else {
+                    vec![4, 3, 2, 1]
+                };
+                row.into_iter().map(move |x| Position { x, y })
+            })
+            .collect();
+        for (length, fraction) in [(1, 1.0 / 16.0), (8, 0.5), (16, 1.0)] {
+            let body = path[..length].iter().rev().copied().collect();
+            let game = Game::new(GameConfig {
+                board: board.clone(),
+                ..GameConfig::default().with_snake(Snake::init(body).unwrap())
+            });
+            assert_eq!(game.fill_fraction(), fraction);
+        }
+    }
+
+    #[test]
+    fn food_avoiding_the_tail_never_spawns_beside_it() {
+        // On a 4x4 floor, the tail at (1, 2) has free neighbors (1, 1) and
+        // (1, 3), leaving eleven other free cells
+        let body = vec![
+            Position { x: 3, y: 2 },
+            Position { x: 2, y: 2 },
+            Position { x: 1, y: 2 },
+        ];
+        let near_tail = [Position { x: 1, y: 1 }, Position { x: 1, y: 3 }];
+        let spawned = |avoid| {
+            let mut game = Game::new(GameConfig {
+                board: Board::new(6, 6),
+                food_avoids_tail: avoid,
+                seed: Some(3),
+                ..GameConfig::default().with_snake(Snake::init(body.clone()).unwrap())
+            });
+            (0..200)
+                .map(|_| {
+                    game.food.clear();
+                    assert!(game.spawn_food());
+                    game.food[0].position
+                })
+                .collect::<Vec<_>>()
+        };
+        for position in spawned(true) {
+            assert!(!near_tail.contains(&position));
+            assert!(!body.contains(&position));
+        }
+        // Without the setting the same cells do come up
+        assert!(spawned(false)
+            .iter()
+            .any(|position| near_tail.contains(position)));
+    }
+
+    #[test]
+    fn position_distance_and_direction() {
+        let at = |x, y| Position { x, y };
+        let from = at(5, 5);
+        let cases = [
+            (at(5, 5), 0, None),
+            (at(5, 4), 1, Some(Direction::Up)),
+            (at(5, 6), 1, Some(Direction::Down)),
+            (at(4, 5), 1, Some(Direction::Left)),
+            (at(6, 5), 1, Some(Direction::Right)),
+            (at(6, 6), 2, None),
+            (at(4, 4), 2, None),
+            (at(5, 7), 2, None),
+            (at(0, 9), 9, None),
+        ];
+        for (to, distance, direction) in cases {
+            assert_eq!(from.manhattan_distance(to), distance, "to {to}");
+            assert_eq!(to.manhattan_distance(from), distance, "from {to}");
+            assert_eq!(from.direction_to(to), direction, "to {to}");
+        }
+    }
+
+    // A snake heading Up from the middle of the board, with a current
+    // pushing it Right every three ticks
+    fn game_with_drift() -> Game {
+        let snake = Snake::with(vec![Board::default().center()], Direction::Up, 0).unwrap();
+        Game::new(GameConfig {
+            drift: Some(Drift {
+                direction: Direction::Right,
+                every_ticks: 3,
+            }),
+            initial_food: vec![Position { x: 1, y: 1 }],
+            ..GameConfig::default().with_snake(snake)
+        })
+    }
+
+    #[test]
+    fn drift_pushes_an_idle_snake_on_schedule() {
+        let mut game = game_with_drift();
+        game.step();
+        game.step();
+        assert_eq!(game.snake().direction(), Direction::Up);
+        game.step();
+        assert_eq!(game.snake().direction(), Direction::Right);
+    }
+
+    #[test]
+    fn drift_leaves_a_steered_snake_alone() {
+        let mut game = game_with_drift();
+        game.turn(Direction::Left);
+        game.step();
+        game.turn(Direction::Up);
+        game.step();
+        game.step();
+        assert_eq!(game.snake().direction(), Direction::Up);
+        // Left alone until the next push, it drifts again
+        for _ in 0..3 {
+            game.step();
+        }
+        assert_eq!(game.snake().direction(), Direction::Right);
+    }
+
+    #[test]
+    fn identical_games_hash_identically() {
+        let new_game = || {
+            Game::new(GameConfig {
+                seed: Some(9),
+                max_food: 3,
+                ..GameConfig::default()
+            })
+        };
+        let turns = [
+            (5, Direction::Up),
+            (10, Direction::Left),
+            (20, Direction::Down),
+        ];
+        let mut first = new_game();
+        let mut second = new_game();
+        assert_eq!(first.state_hash(), second.state_hash());
+        for tick in 0..30 {
+            if let Some((_, direction)) = turns.iter().find(|(at, _)| *at == tick) {
+                first.turn(*direction);
+                second.turn(*direction);
+            }
+            first.step();
+            second.step();
+            assert_eq!(first.state_hash(), second.state_hash(), "tick {tick}");
+        }
+
+        first.turn(Direction::Left);
+        second.turn(Direction::Right);
+        assert_ne!(first.state_hash(), second.state_hash());
+        first.step();
+        second.step();
+        assert_ne!(first.state_hash(), second.state_hash());
+    }
+}
@@ -0,0 +1,322 @@
+// Small pieces of state kept between runs, stored as plain text files in a
+// per-user data directory: `$XDG_DATA_HOME/rusty-snake`, falling back to
+// `~/.local/share/rusty-snake`, or `%APPDATA%\rusty-snake` on Windows.
+//
+// Preferences go in a `config` file of `key = value` lines. Blank lines,
+// lines starting with `#` and unknown keys are ignored when reading, and
+// comments and unknown keys are carried over when saving, so builds that add
+// keys can share one file. A `version` key marks files older builds must
+// refuse instead.
+
+use crate::Error;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const APP_DIR: &str = "rusty-snake";
+const HIGH_SCORE_FILE: &str = "high_score";
+const CONFIG_FILE: &str = "config";
+// Bumped when the config file changes in a way older builds can't read
+const CONFIG_VERSION: u32 = 1;
+// Keys this build writes itself; any others are left as they were
+const CONFIG_KEYS: [&str; 6] = [
+    "version",
+    "theme",
+    "refresh_rate",
+    "wall_grace",
+    "window_position",
+    "window_size",
+];
+
+/// Preferences remembered from one run to the next.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    /// Name of the last theme picked with `T` or on the options screen.
+    pub theme: Option<String>,
+    /// Milliseconds per step, as last set on the options screen.
+    pub refresh_rate: Option<u64>,
+    /// Whether walls hold the snake for a tick before killing it, as last
+    /// set on the options screen.
+    pub wall_grace: Option<bool>,
+    /// Where the window's top-left corner was when the game last closed,
+    /// in screen pixels. Either coordinate can be negative on setups with a
+    /// monitor left of or above the primary one.
+    pub window_position: Option<(isize, isize)>,
+    /// The window's size in pixels when the game last closed.
+    pub window_size: Option<(usize, usize)>,
+}
+
+/// The directory saved state lives in.
+pub fn data_dir() -> Result<PathBuf, Error> {
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .ok_or(Error::NoDataDir)?;
+    Ok(base.join(APP_DIR))
+}
+
+/// The best score saved so far, or 0 if none has been saved yet.
+pub fn load_high_score() -> Result<u32, Error> {
+    let path = data_dir()?.join(HIGH_SCORE_FILE);
+    let Some(contents) = read_if_exists(&path)? else {
+        return Ok(0);
+    };
+    contents.trim().parse().map_err(|e| Error::Parse {
+        path,
+        line: 1,
+        message: format!("invalid high score: {}", e),
+    })
+}
+
+/// Saves `score` as the new best, creating the data directory if needed.
+pub fn save_high_score(score: u32) -> Result<(), Error> {
+    write(HIGH_SCORE_FILE, format!("{}\n", score))
+}
+
+/// The saved preferences, or the defaults if none have been saved yet.
+/// Fails if the file was written by a newer, incompatible version.
+pub fn load_config() -> Result<Config, Error> {
+    let path = data_dir()?.join(CONFIG_FILE);
+    match read_if_exists(&path)? {
+        Some(contents) => parse_config(&contents, &path),
+        None => Ok(Config::default()),
+    }
+}
+
+// The preferences in `contents`, read from the config file at `path`
+fn parse_config(contents: &str, path: &Path) -> Result<Config, Error> {
+    let mut config = Config::default();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(Error::Parse {
+                path: path.to_path_buf(),
+                line: i + 1,
+                message: format!("expected `key = value`, found `{}`", line),
+            });
+        };
+        let value = value.trim();
+        match key.trim() {
+            "version" => {
+                let found = value.parse().map_err(|e| Error::Parse {
+                    path: path.to_path_buf(),
+                    line: i + 1,
+                    message: format!("invalid version: {}", e),
+                })?;
+                if found > CONFIG_VERSION {
+                    return Err(Error::VersionMismatch {
+                        path: path.to_path_buf(),
+                        found,
+                        supported: CONFIG_VERSION,
+                    });
+                }
+            }
+            "theme" => config.theme = Some(value.to_string()),
+            "refresh_rate" => config.refresh_rate = Some(parse_value(value, path, i + 1)?),
+            "wall_grace" => config.wall_grace = Some(parse_value(value, path, i + 1)?),
+            "window_position" => {
+                config.window_position = Some(parse_pair(value, ',', path, i + 1)?);
+            }
+            "window_size" => config.window_size = Some(parse_pair(value, 'x', path, i + 1)?),
+            _ => {}
+        }
+    }
+    Ok(config)
+}
+
+/// Saves `config`. Comments and keys this build doesn't know, such as ones
+/// written by a newer build, are kept from the file being replaced.
+pub fn save_config(config: &Config) -> Result<(), Error> {
+    let existing = read_if_exists(&data_dir()?.join(CONFIG_FILE))?;
+    write(CONFIG_FILE, config_contents(config, existing.as_deref()))
+}
+
+// The text of a config file holding `config`, followed by the comments and
+// unknown keys of the `existing` file it replaces. Lines that aren't either
+// are dropped, as they would only stop the file loading.
+fn config_contents(config: &Config, existing: Option<&str>) -> String {
+    let mut contents = format!("version = {}\n", CONFIG_VERSION);
+    if let Some(theme) = &config.theme {
+        contents.push_str(&format!("theme = {}\n", theme));
+    }
+    if let Some(refresh_rate) = config.refresh_rate {
+        contents.push_str(&format!("refresh_rate = {}\n", refresh_rate));
+    }
+    if let Some(wall_grace) = config.wall_grace {
+        contents.push_str(&format!("wall_grace = {}\n", wall_grace));
+    }
+    if let Some((x, y)) = config.window_position {
+        contents.push_str(&format!("window_position = {}, {}\n", x, y));
+    }
+    if let Some((width, height)) = config.window_size {
+        contents.push_str(&format!("window_size = {}x{}\n", width, height));
+    }
+    for line in existing.unwrap_or_default().lines() {
+        let kept = match line.trim().split_once('=') {
+            _ if line.trim().starts_with('#') => true,
+            Some((key, _)) => !CONFIG_KEYS.contains(&key.trim()),
+            None => false,
+        };
+        if kept {
+            contents.push_str(line);
+            contents.push('\n');
+        }
+    }
+    contents
+}
+
+// Two numbers separated by `separator`, such as `10, 20` or `1280x720`
+fn parse_pair<T>(value: &str, separator: char, path: &Path, line: usize) -> Result<(T, T), Error>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let error = |message: String| Error::Parse {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+    let (first, second) = value
+        .split_once(separator)
+        .ok_or_else(|| error(format!("expected two numbers separated by `{}`", separator)))?;
+    let parse = |number: &str| {
+        number
+            .trim()
+            .parse()
+            .map_err(|e| error(format!("invalid number `{}`: {}", number.trim(), e)))
+    };
+    Ok((parse(first)?, parse(second)?))
+}
+
+// A single value such as `150` or `true`
+fn parse_value<T>(value: &str, path: &Path, line: usize) -> Result<T, Error>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value.parse().map_err(|e| Error::Parse {
+        path: path.to_path_buf(),
+        line,
+        message: format!("invalid value `{}`: {}", value, e),
+    })
+}
+
+// The file's contents, or None if it doesn't exist yet
+fn read_if_exists(path: &Path) -> Result<Option<String>, Error> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::io(path, e)),
+    }
+}
+
+// Write a file in the data directory, creating the directory if needed
+fn write(file: &str, contents: String) -> Result<(), Error> {
+    let dir = data_dir()?;
+    fs::create_dir_all(&dir).map_err(|e| Error::io(&dir, e))?;
+    let path = dir.join(file);
+    fs::write(&path, contents).map_err(|e| Error::io(&path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_config_reads_every_key() {
+        let contents = "version = 1\n\
+                        # a comment\n\
+                        \n\
+                        theme = ocean\n\
+                        refresh_rate = 100\n\
+                        wall_grace = true\n\
+                        window_position = -1920, 40\n\
+                        window_size = 1280x720\n\
+                        sound_volume = 7\n";
+        let config = parse_config(contents, Path::new("config")).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                theme: Some("ocean".to_string()),
+                refresh_rate: Some(100),
+                wall_grace: Some(true),
+                window_position: Some((-1920, 40)),
+                window_size: Some((1280, 720)),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_config_reports_the_line_of_a_bad_value() {
+        let error = parse_config("theme = ocean\nrefresh_rate = fast\n", Path::new("config"));
+        assert!(matches!(error, Err(Error::Parse { line: 2, .. })));
+        let error = parse_config("window_size = 1280\n", Path::new("config"));
+        assert!(matches!(error, Err(Error::Parse { line: 1, .. })));
+    }
+
+    #[test]
+    fn parse_config_rejects_a_line_without_a_value() {
+        let error = parse_config("theme ocean\n", Path::new("config"));
+        assert!(matches!(error, Err(Error::Parse { line: 1, .. })));
+    }
+
+    #[test]
+    fn parse_config_refuses_newer_versions() {
+        let error = parse_config("version = 2\n", Path::new("config"));
+        assert!(matches!(
+            error,
+            Err(Error::VersionMismatch {
+                found: 2,
+                supported: CONFIG_VERSION,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn config_contents_writes_every_set_field() {
+        let config = Config {
+            theme: Some("ocean".to_string()),
+            refresh_rate: Some(100),
+            wall_grace: Some(true),
+            window_position: Some((-1920, 40)),
+            window_size: Some((1280, 720)),
+        };
+        assert_eq!(
+            config_contents(&config, None),
+            "version = 1\n\
+             theme = ocean\n\
+             refresh_rate = 100\n\
+             wall_grace = true\n\
+             window_position = -1920, 40\n\
+             window_size = 1280x720\n"
+        );
+    }
+
+    #[test]
+    fn config_contents_keeps_comments_and_unknown_keys() {
+        let existing = "version = 1\n\
+                        # picked by hand\n\
+                        theme = forest\n\
+                        sound_volume = 7\n\
+                        \n\
+                        not a setting\n";
+        let config = Config {
+            theme: Some("ocean".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(
+            config_contents(&config, Some(existing)),
+            "version = 1\n\
+             theme = ocean\n\
+             # picked by hand\n\
+             sound_volume = 7\n"
+        );
+    }
+}
@@ -0,0 +1,33 @@
+// Hooks for code embedding the game to watch it play out, such as logging,
+// achievements or sound, without the game knowing about any of them.
+
+use crate::{FoodKind, Game, Position};
+
+/// Callbacks the game makes while it steps. Pass one to `Game::update_with`
+/// or `Game::step_with`; every method has an empty default, so implement
+/// only the ones you need.
+///
+/// Callbacks run synchronously on the thread stepping the game, in the
+/// middle of the step, and get the game as it stands at that point. Nothing
+/// is required to be `Send` or `Sync`.
+pub trait Telemetry {
+    /// After every step, once everything else in it has happened.
+    fn on_tick(&mut self, _game: &Game) {}
+
+    /// The snake ate food of `kind` at `position`, scoring `points`. The
+    /// replacement food has already been placed.
+    fn on_eat(&mut self, _game: &Game, _position: Position, _kind: FoodKind, _points: u32) {}
+
+    /// The snake hit a wall or itself.
+    fn on_death(&mut self, _game: &Game) {}
+
+    /// The board filled up or the win condition was reached.
+    fn on_win(&mut self, _game: &Game) {}
+}
+
+/// Telemetry that ignores everything, used by `Game::update` and
+/// `Game::step`. Its callbacks compile away to nothing.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoTelemetry;
+
+impl Telemetry for NoTelemetry {}
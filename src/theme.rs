@@ -0,0 +1,174 @@
+// Color palettes for the playfield. Colors are 0xRRGGBB, the format minifb
+// expects in the frame buffer.
+
+use rand::Rng;
+
+// Minimum difference in perceived brightness (0-255) between a generated
+// color and the background, so nothing blends into it.
+const MIN_CONTRAST: f32 = 110.0;
+
+/// The colors used to draw each element of the board.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+    pub background: u32,
+    pub snake: u32,
+    pub food: u32,
+    pub border: u32,
+}
+
+impl Theme {
+    /// Green snake and red food on black, with a white border.
+    pub const CLASSIC: Theme = Theme {
+        background: 0x000000,
+        snake: 0x00FF00,
+        food: 0xFF0000,
+        border: 0xFFFFFF,
+    };
+
+    /// Orange and amber on a dark brown, like an old amber monitor.
+    pub const AMBER: Theme = Theme {
+        background: 0x1A1000,
+        snake: 0xFFB000,
+        food: 0xFF4D00,
+        border: 0xFFE0A0,
+    };
+
+    /// Teal snake and coral food on deep blue.
+    pub const OCEAN: Theme = Theme {
+        background: 0x001B2E,
+        snake: 0x00D1C1,
+        food: 0xFF6F59,
+        border: 0xA8D8FF,
+    };
+
+    /// The four greens of an early handheld screen.
+    pub const HANDHELD: Theme = Theme {
+        background: 0x0F380F,
+        snake: 0x8BAC0F,
+        food: 0xE0F8D0,
+        border: 0x9BBC0F,
+    };
+
+    /// Every built-in palette with its name, in the order `T` cycles through
+    /// them.
+    pub const PRESETS: [(&'static str, Theme); 4] = [
+        ("classic", Theme::CLASSIC),
+        ("amber", Theme::AMBER),
+        ("ocean", Theme::OCEAN),
+        ("handheld", Theme::HANDHELD),
+    ];
+
+    /// The built-in palette called `name`.
+    pub fn preset(name: &str) -> Option<Theme> {
+        Theme::PRESETS
+            .iter()
+            .find(|(preset, _)| *preset == name)
+            .map(|(_, theme)| *theme)
+    }
+
+    /// The name of this palette if it is one of the built-in ones.
+    pub fn preset_name(&self) -> Option<&'static str> {
+        Theme::PRESETS
+            .iter()
+            .find(|(_, theme)| theme == self)
+            .map(|(name, _)| *name)
+    }
+
+    /// The built-in palette after this one, wrapping around at the end.
+    /// Palettes that aren't built in, such as random ones, go to the first.
+    pub fn next_preset(&self) -> Theme {
+        let next = Theme::PRESETS
+            .iter()
+            .position(|(_, theme)| theme == self)
+            .map_or(0, |i| (i + 1) % Theme::PRESETS.len());
+        Theme::PRESETS[next].1
+    }
+
+    /// The built-in palette before this one, wrapping around at the start.
+    /// Palettes that aren't built in go to the last.
+    pub fn previous_preset(&self) -> Theme {
+        let len = Theme::PRESETS.len();
+        let previous = Theme::PRESETS
+            .iter()
+            .position(|(_, theme)| theme == self)
+            .map_or(len - 1, |i| (i + len - 1) % len);
+        Theme::PRESETS[previous].1
+    }
+
+    /// A palette derived from `rng`, on the classic black background. The
+    /// snake and food get vivid colors at least a third of the color wheel
+    /// apart, and the border a pale tint, all bright enough to stand out.
+    pub fn random(rng: &mut impl Rng) -> Self {
+        let background = Theme::CLASSIC.background;
+        let snake_hue = rng.random_range(0.0..360.0);
+        let food_hue = snake_hue + rng.random_range(120.0..240.0);
+        Theme {
+            background,
+            snake: vivid(rng, snake_hue, background),
+            food: vivid(rng, food_hue, background),
+            border: contrasting(background, || {
+                hsv(
+                    rng.random_range(0.0..360.0),
+                    0.2,
+                    rng.random_range(0.85..1.0),
+                )
+            }),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::CLASSIC
+    }
+}
+
+fn vivid(rng: &mut impl Rng, hue: f32, background: u32) -> u32 {
+    contrasting(background, || {
+        hsv(hue, rng.random_range(0.6..1.0), rng.random_range(0.75..1.0))
+    })
+}
+
+// Brighten a generated color until it is far enough from the background
+fn contrasting(background: u32, mut generate: impl FnMut() -> u32) -> u32 {
+    let color = generate();
+    if (brightness(color) - brightness(background)).abs() >= MIN_CONTRAST {
+        return color;
+    }
+    lighten(color)
+}
+
+// Perceived brightness using the Rec. 601 luma weights
+fn brightness(color: u32) -> f32 {
+    let r = ((color >> 16) & 0xFF) as f32;
+    let g = ((color >> 8) & 0xFF) as f32;
+    let b = (color & 0xFF) as f32;
+    0.299 * r + 0.587 * g + 0.114 * b
+}
+
+// Mix a color halfway toward white, which always clears MIN_CONTRAST
+// against a black background
+fn lighten(color: u32) -> u32 {
+    let channel = |shift: u32| {
+        let c = (color >> shift) & 0xFF;
+        (c + (0xFF - c) / 2) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
+
+fn hsv(hue: f32, saturation: f32, value: f32) -> u32 {
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    let to_byte = |c: f32| ((c + m) * 255.0).round() as u32;
+    (to_byte(r) << 16) | (to_byte(g) << 8) | to_byte(b)
+}